@@ -0,0 +1,66 @@
+//! Plays every level with the auto player to see whether its time limit can be met.
+//!
+//! `cargo run --release --example benchmark -- [seeds]`
+
+use std::time::Duration;
+
+use sfu::{
+    game::{auto_player::AutoPlayer, campaign::LEVELS, GameData, GameTimer, LevelConfig},
+    simulation::Simulation,
+    state::AppState,
+};
+
+const HEADROOM: f32 = 1.2; // Suggested limits leave people this much longer than the bot needed
+const OVERTIME: u32 = 3; // Let the bot keep going past the limit so slow runs still finish
+
+fn main() {
+    let seeds = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse::<u64>().ok())
+        .unwrap_or(5);
+
+    for (index, level) in LEVELS.iter().enumerate() {
+        let mut limit = 0;
+        let mut times = Vec::new();
+
+        for seed in 1..=seeds {
+            let mut sim = Simulation::new(seed);
+            sim.app.insert_resource(AutoPlayer::default());
+            sim.start_level(index);
+
+            limit = sim.app.world.resource::<LevelConfig>().time;
+            sim.app
+                .world
+                .resource_mut::<GameTimer>()
+                .set_duration(Duration::from_secs(limit as u64 * OVERTIME as u64));
+
+            sim.run_until(f32::MAX, |sim| sim.state() == AppState::PostGame);
+
+            let data = sim.app.world.resource::<GameData>();
+            let elapsed = sim.app.world.resource::<GameTimer>().elapsed_secs();
+            if data.won {
+                println!("{} seed {}: cleared in {:.0}s", level.name, seed, elapsed);
+                times.push(elapsed);
+            } else {
+                println!(
+                    "{} seed {}: collected {} and broke {} in {:.0}s",
+                    level.name, seed, data.presents_collected, data.presents_broken, elapsed
+                );
+            }
+        }
+
+        let Some(slowest) = times.iter().copied().reduce(f32::max) else {
+            println!("{}: never cleared. Limit is {}s\n", level.name, limit);
+            continue;
+        };
+        println!(
+            "{}: cleared {}/{} runs, slowest {:.0}s. Limit is {}s, suggest {}s\n",
+            level.name,
+            times.len(),
+            seeds,
+            slowest,
+            limit,
+            (slowest * HEADROOM).ceil()
+        );
+    }
+}
@@ -2,3 +2,7 @@ use bevy::{asset::UntypedAssetId, ecs::system::Resource};
 
 #[derive(Resource, Default)]
 pub struct LoadingAssets(pub Vec<UntypedAssetId>);
+
+/// Why the last level could not be loaded. Exists from the failure until loading starts again
+#[derive(Resource, Debug)]
+pub struct LoadError(pub String);
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use leafwing_input_manager::action_state::ActionState;
+
+use crate::coord::GROUND_DISTANCE;
+use crate::game::{
+    ground_station::GroundStation,
+    present::Present,
+    rocket::{Cargo, Rocket},
+    trajectory, PlayerAction,
+};
+use crate::input::Pointer;
+use crate::Coord;
+
+use super::orbit::OrbitCamera;
+
+const PICK_RADIUS: f32 = 60.0; // How close in pixels the cursor has to be to something to focus it
+const FOCUS_OFFSET: f32 = 25.0; // How far above the focused thing the camera stops
+
+/// Flies the camera to a present, rocket or station and follows it
+#[derive(Event)]
+pub struct FocusCamera(pub Entity);
+
+// Focuses whatever is closest to the cursor when the focus action is pressed
+#[allow(clippy::type_complexity)]
+pub fn pick(
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    action_query: Query<&ActionState<PlayerAction>>,
+    target_query: Query<
+        (Entity, &GlobalTransform),
+        (
+            Or<(With<Present>, With<Rocket>, With<GroundStation>)>,
+            Without<Cargo>,
+        ),
+    >,
+    pointer: Res<Pointer>,
+    mut focus_events: EventWriter<FocusCamera>,
+) {
+    let Ok(action) = action_query.get_single() else {
+        return;
+    };
+    if !action.just_pressed(PlayerAction::Focus) {
+        return;
+    }
+
+    let Some(cursor_position) = pointer.position else {
+        return;
+    };
+
+    for (camera, camera_transform) in &cameras {
+        let eye = camera_transform.translation();
+
+        let closest = target_query
+            .iter()
+            .filter(|(_, transform)| {
+                // Skip anything hidden behind the earth. Stations sit right on the surface
+                trajectory::segment_min_distance(eye, transform.translation())
+                    >= GROUND_DISTANCE - 0.5
+            })
+            .filter_map(|(entity, transform)| {
+                let screen_pos =
+                    camera.world_to_viewport(camera_transform, transform.translation())?;
+                Some((entity, screen_pos.distance(cursor_position)))
+            })
+            .filter(|(_, distance)| *distance < PICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((entity, _)) = closest {
+            focus_events.send(FocusCamera(entity));
+        }
+    }
+}
+
+// Keeps the camera pointed at the focused entity until the player moves it or it is gone
+pub fn follow(
+    mut focus_events: EventReader<FocusCamera>,
+    mut camera_query: Query<(&Coord, &mut OrbitCamera)>,
+    transform_query: Query<&GlobalTransform>,
+) {
+    for (coord, mut camera) in camera_query.iter_mut() {
+        let mut dist = camera.target.get_distance();
+
+        if let Some(FocusCamera(entity)) = focus_events.read().last() {
+            if let Ok(transform) = transform_query.get(*entity) {
+                camera.focus = Some(*entity);
+                dist = transform.translation().length() + FOCUS_OFFSET;
+            }
+        }
+
+        let Some(entity) = camera.focus else {
+            continue;
+        };
+        match transform_query.get(entity) {
+            Ok(transform) => camera.look_at(coord, transform.translation(), dist),
+            Err(_) => camera.focus = None,
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use bevy::prelude::*;
 
+use crate::state::{AppState, GameState};
+
+pub mod focus;
 pub mod orbit;
 
 pub struct CameraPlugin;
@@ -7,6 +10,17 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_systems(Startup, orbit::init)
-            .add_systems(Update, orbit::update_input);
+            .add_event::<focus::FocusCamera>()
+            .add_systems(
+                Update,
+                (
+                    orbit::update_input,
+                    focus::pick
+                        .run_if(in_state(AppState::InGame).and_then(in_state(GameState::Running))),
+                    focus::follow,
+                    orbit::update,
+                )
+                    .chain(),
+            );
     }
 }
@@ -1,3 +1,5 @@
+use std::f32::consts::{PI, TAU};
+
 use bevy::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
 
@@ -5,49 +7,138 @@ use crate::state::{GameState, InteractionState};
 use crate::{coord::CoordDistance, Coord};
 
 use crate::game::PlayerAction;
+use crate::settings::Settings;
 
 // For some reason mouse zoom is very different on web
 #[cfg(target_arch = "wasm32")]
 const ZOOM_SCALER: f32 = 1.0;
 #[cfg(not(target_arch = "wasm32"))]
 const ZOOM_SCALER: f32 = 100.0;
+const ORBIT_RATE: f32 = 1.5; // Radians per second at full stick or with a key held
+const ZOOM_RATE: f32 = 40.0; // Distance per second with a trigger or key fully held
+const SMOOTHING: f32 = 10.0; // How quickly held inputs reach full speed. Higher is snappier
+const SMOOTH_TIME: f32 = 0.12; // Roughly how long in seconds the camera takes to catch up to its target
+const FRICTION: f32 = 4.0; // How quickly the earth stops spinning after a drag
+const MAX_LAT: f32 = 1.4; // Stop short of the poles so the camera never flips over
+pub const MIN_DISTANCE: f32 = 30.0;
+pub const MAX_DISTANCE: f32 = 100.0;
+pub const DEFAULT_DISTANCE: f32 = 80.0;
+
+/// The camera eases its `Coord` towards `target`, which is what input moves
+#[derive(Component)]
+pub struct OrbitCamera {
+    pub target: Coord,
+    pub focus: Option<Entity>, // Followed until the player moves the camera themselves
+    damping: Vec3,             // Current longitude, latitude and distance change per second
+    momentum: Vec2,            // Spin left over from a drag
+    velocity: Vec2,            // Longitude and latitude change per second from held inputs
+    zoom_velocity: f32,
+}
 
-#[derive(Component, Default)]
-pub struct OrbitCamera {}
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            target: Coord::from_dist(DEFAULT_DISTANCE),
+            focus: None,
+            damping: Vec3::ZERO,
+            momentum: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            zoom_velocity: 0.0,
+        }
+    }
+}
 
 impl OrbitCamera {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Points the camera at `position` from `dist` away, taking the short way round
+    pub fn look_at(&mut self, current: &Coord, position: Vec3, dist: f32) {
+        let coord = Coord::from_translation(position);
+        self.target.long = current.long + wrap_angle(coord.long - current.long);
+        self.target.lat = coord.lat.clamp(-MAX_LAT, MAX_LAT);
+        self.target.dist = CoordDistance::Orbit(dist.clamp(MIN_DISTANCE, MAX_DISTANCE));
+        self.momentum = Vec2::ZERO;
+    }
+
+    fn set_distance(&mut self, dist: f32) {
+        self.target.dist = CoordDistance::Orbit(dist.clamp(MIN_DISTANCE, MAX_DISTANCE));
+    }
+}
+
+/// Moves `current` towards `target` like a critically damped spring so it settles without
+/// overshooting. `velocity` carries over between calls
+pub fn smooth_damp(
+    current: f32,
+    target: f32,
+    velocity: &mut f32,
+    smooth_time: f32,
+    dt: f32,
+) -> f32 {
+    let omega = 2.0 / smooth_time.max(0.0001);
+    let x = omega * dt;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + omega * change) * dt;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (change + temp) * decay
+}
+
+// Angle between -PI and PI
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
 }
 
 pub fn init(mut _commands: Commands) {}
 
 pub fn update_input(
     time: Res<Time>,
-    mut camera_query: Query<(&mut Coord, &ActionState<PlayerAction>)>,
+    mut camera_query: Query<(&Coord, &mut OrbitCamera, &ActionState<PlayerAction>)>,
     game_state: Res<State<GameState>>,
     interaction_state: Res<State<InteractionState>>,
+    settings: Res<Settings>,
 ) {
     // If game is paused dont update
     if *game_state.get() == GameState::Paused {
         return;
     }
 
-    // Dont move if we clicked an entity
-    if *interaction_state == InteractionState::OnEntity {
+    // Dont move if we clicked an entity or are dragging a selection box
+    if *interaction_state != InteractionState::Idle {
         return;
     }
 
-    for (mut coord, action) in camera_query.iter_mut() {
+    // Same easing whatever the framerate
+    let dt = time.delta_seconds();
+    let blend = 1.0 - (-SMOOTHING * dt).exp();
+
+    for (coord, mut camera, action) in camera_query.iter_mut() {
+        if action.just_pressed(PlayerAction::ResetCamera) {
+            *camera = OrbitCamera {
+                damping: camera.damping,
+                ..default()
+            };
+            camera.target.long = coord.long + wrap_angle(-coord.long);
+            continue;
+        }
+
         if action.pressed(PlayerAction::Zoom) {
-            let zoom_delta = action.value(PlayerAction::Zoom) * time.delta_seconds() * ZOOM_SCALER;
-            coord.dist =
-                CoordDistance::Orbit((coord.get_distance() - zoom_delta).clamp(30.0, 100.0));
+            let zoom_delta =
+                action.value(PlayerAction::Zoom) * dt * ZOOM_SCALER * settings.zoom_speed;
+            let dist = camera.target.get_distance() - zoom_delta;
+            camera.set_distance(dist);
+        }
+
+        let target_zoom = action.value(PlayerAction::ZoomAxis) * ZOOM_RATE * settings.zoom_speed;
+        camera.zoom_velocity += (target_zoom - camera.zoom_velocity) * blend;
+        if camera.zoom_velocity != 0.0 {
+            let dist = camera.target.get_distance() - camera.zoom_velocity * dt;
+            camera.set_distance(dist);
         }
 
         let move_delta = match action.axis_pair(PlayerAction::Move) {
-            Some(axis) => axis.xy() * time.delta_seconds() * 0.1,
+            Some(axis) => axis.xy() * dt * 0.1 * settings.camera_sensitivity,
             None => Vec2::ZERO,
         };
 
@@ -56,8 +147,123 @@ pub fn update_input(
             && action.just_pressed(PlayerAction::CanMove) == false
         // Ignore if we just pressed to allow us time to stop false moved when on an entity
         {
-            coord.long -= move_delta.x;
-            coord.lat += move_delta.y;
+            let drag = Vec2::new(-move_delta.x, move_delta.y);
+            camera.target.long += drag.x;
+            camera.target.lat += drag.y;
+            if dt > 0.0 {
+                // Remember how fast the earth was spun so it keeps going once let go
+                camera.momentum = camera.momentum.lerp(drag / dt, blend);
+            }
+            if drag != Vec2::ZERO {
+                camera.focus = None;
+            }
+        } else {
+            let momentum = camera.momentum;
+            camera.target.long += momentum.x * dt;
+            camera.target.lat += momentum.y * dt;
+            camera.momentum *= (-FRICTION * dt).exp();
+        }
+
+        // Sticks and keys move the camera rather than dragging the earth so they go the other way
+        let target_velocity = action
+            .axis_pair(PlayerAction::OrbitAxis)
+            .map_or(Vec2::ZERO, |axis| axis.xy().clamp_length_max(1.0))
+            * ORBIT_RATE
+            * settings.camera_sensitivity;
+        camera.velocity = camera.velocity.lerp(target_velocity, blend);
+        let velocity = camera.velocity;
+        camera.target.long += velocity.x * dt;
+        camera.target.lat += velocity.y * dt;
+        if target_velocity != Vec2::ZERO {
+            camera.focus = None;
+            camera.momentum = Vec2::ZERO;
         }
+
+        camera.target.lat = camera.target.lat.clamp(-MAX_LAT, MAX_LAT);
+    }
+}
+
+// Eases the camera towards its target
+pub fn update(time: Res<Time>, mut camera_query: Query<(&mut Coord, &mut OrbitCamera)>) {
+    let dt = time.delta_seconds();
+
+    for (mut coord, mut camera) in camera_query.iter_mut() {
+        let target = camera.target;
+        let mut damping = camera.damping;
+
+        coord.long = smooth_damp(coord.long, target.long, &mut damping.x, SMOOTH_TIME, dt);
+        coord.lat = smooth_damp(coord.lat, target.lat, &mut damping.y, SMOOTH_TIME, dt);
+        coord.dist = CoordDistance::Orbit(smooth_damp(
+            coord.get_distance(),
+            target.get_distance(),
+            &mut damping.z,
+            SMOOTH_TIME,
+            dt,
+        ));
+
+        camera.damping = damping;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_damp_settles_without_overshooting() {
+        let mut value = 0.0;
+        let mut velocity = 0.0;
+        for _ in 0..120 {
+            value = smooth_damp(value, 10.0, &mut velocity, SMOOTH_TIME, 1.0 / 60.0);
+            assert!(value <= 10.0);
+        }
+
+        assert!((value - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn smooth_damp_is_framerate_independent() {
+        let run = |steps: usize| {
+            let mut value = 0.0;
+            let mut velocity = 0.0;
+            for _ in 0..steps {
+                value = smooth_damp(value, 10.0, &mut velocity, SMOOTH_TIME, 0.1 / steps as f32);
+            }
+            value
+        };
+
+        assert!((run(3) - run(30)).abs() < 0.2);
+    }
+
+    #[test]
+    fn look_at_takes_the_short_way_round() {
+        let mut camera = OrbitCamera::new();
+        let current = Coord {
+            long: 3.0,
+            ..Coord::from_dist(DEFAULT_DISTANCE)
+        };
+        // Just past PI on the other side is a short hop forwards, not most of a turn back
+        camera.look_at(
+            &current,
+            Coord {
+                long: -3.0,
+                ..Coord::from_dist(25.0)
+            }
+            .to_transform()
+            .translation,
+            50.0,
+        );
+
+        assert!((camera.target.long - (TAU - 3.0)).abs() < 0.001);
+        assert_eq!(camera.target.get_distance(), 50.0);
+    }
+
+    #[test]
+    fn look_at_stays_off_the_poles() {
+        let mut camera = OrbitCamera::new();
+        camera.look_at(&Coord::new(), Vec3::Y * 30.0, 200.0);
+
+        assert_eq!(camera.target.lat, MAX_LAT);
+        assert_eq!(camera.target.get_distance(), MAX_DISTANCE);
     }
 }
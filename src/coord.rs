@@ -1,5 +1,8 @@
 use bevy::prelude::*;
 
+// Radius of the earth
+pub const GROUND_DISTANCE: f32 = 20.0;
+
 #[derive(Default, Copy, Clone)]
 pub enum CoordDistance {
     #[default]
@@ -36,6 +39,18 @@ impl Coord {
         }
     }
 
+    /// Coordinate pointing at `position` from the centre of the earth
+    pub fn from_translation(position: Vec3) -> Self {
+        let dist = position.length();
+        Self {
+            long: position.x.atan2(position.z),
+            lat: (position.y / dist.max(f32::EPSILON))
+                .clamp(-1.0, 1.0)
+                .asin(),
+            dist: CoordDistance::Orbit(dist),
+        }
+    }
+
     pub fn apply(&self, transform: &mut Transform) {
         transform.rotation = self.get_rotation();
         transform.translation = self.get_translation(transform.rotation);
@@ -57,8 +72,27 @@ impl Coord {
 
     pub fn get_distance(&self) -> f32 {
         match self.dist {
-            CoordDistance::Ground => 20.0,
+            CoordDistance::Ground => GROUND_DISTANCE,
             CoordDistance::Orbit(dist) => dist,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_translation_round_trips() {
+        let coord = Coord {
+            long: 2.0,
+            lat: -0.7,
+            dist: CoordDistance::Orbit(42.0),
+        };
+        let back = Coord::from_translation(coord.to_transform().translation);
+
+        assert!((back.long - coord.long).abs() < 0.001);
+        assert!((back.lat - coord.lat).abs() < 0.001);
+        assert!((back.get_distance() - 42.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    state::{AppState, GameState},
+    Coord,
+};
+
+use super::{
+    ground_station::{self, GroundStation, Upgrade, UpgradeStation},
+    orbit::Orbit,
+    present::{CollectPresent, Present, PresentKind, SelectedMaterial},
+    rocket::{Cargo, Rocket, RocketTarget},
+    GameData, GameTimer,
+};
+
+const THINK_INTERVAL: f32 = 0.25; // Seconds between decisions, roughly how fast a person clicks
+
+// Plays the game whenever an `AutoPlayer` resource is present
+pub struct AutoPlayerPlugin;
+
+impl Plugin for AutoPlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            play.before(ground_station::apply_upgrades)
+                .run_if(resource_exists::<AutoPlayer>())
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// Marks presents for collection in place of the player. Insert it to start playing and
+/// remove it to stop
+#[derive(Resource)]
+pub struct AutoPlayer {
+    strategy: Box<dyn Strategy>,
+    timer: Timer,
+}
+
+impl AutoPlayer {
+    pub fn new(strategy: impl Strategy) -> Self {
+        Self {
+            strategy: Box::new(strategy),
+            timer: Timer::from_seconds(THINK_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl Default for AutoPlayer {
+    fn default() -> Self {
+        Self::new(Greedy)
+    }
+}
+
+/// Decides which presents to mark given what is going on in the level
+pub trait Strategy: Send + Sync + 'static {
+    fn choose(&mut self, view: &View) -> Vec<Order>;
+}
+
+/// Everything a strategy gets to look at
+#[derive(Debug, Default)]
+pub struct View {
+    pub presents: Vec<PresentView>, // Only those not marked yet
+    pub stations: Vec<StationView>,
+    pub pending: usize, // Marked presents no rocket has taken yet
+    pub coins: i32,
+    pub time_left: f32,
+}
+
+#[derive(Debug)]
+pub struct PresentView {
+    pub entity: Entity,
+    pub kind: PresentKind,
+    pub position: Vec3,
+    pub reachable_from: Vec<Entity>, // Stations with a rocket that has the range to get it
+}
+
+#[derive(Debug)]
+pub struct StationView {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub free_slots: usize, // Cargo space on rockets waiting on the pad
+    pub upgrades: Vec<(Upgrade, i32)>, // Upgrades still available and what they cost
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Order {
+    /// Mark `present` for collection, by rockets from `station` if set
+    Collect {
+        present: Entity,
+        station: Option<Entity>,
+    },
+    Upgrade {
+        station: Entity,
+        upgrade: Upgrade,
+    },
+}
+
+// Which upgrade to buy first when they cost the same
+const UPGRADE_PRIORITY: [Upgrade; 3] = [Upgrade::Fleet, Upgrade::Cargo, Upgrade::Speed];
+
+/// Sends each free rocket after the most valuable presents closest to its pad and spends
+/// coins on the station with the most presents in range
+pub struct Greedy;
+
+impl Greedy {
+    fn upgrade(view: &View) -> Option<Order> {
+        let busiest = view.stations.iter().max_by_key(|station| {
+            view.presents
+                .iter()
+                .filter(|present| present.reachable_from.contains(&station.entity))
+                .count()
+        })?;
+        // Faster rockets break fragile presents
+        let fragile = view
+            .presents
+            .iter()
+            .any(|present| present.kind == PresentKind::Fragile);
+        let (upgrade, _) = busiest
+            .upgrades
+            .iter()
+            .filter(|(_, cost)| *cost <= view.coins)
+            .filter(|(upgrade, _)| !(fragile && *upgrade == Upgrade::Speed))
+            .min_by_key(|(upgrade, cost)| {
+                let priority = UPGRADE_PRIORITY.iter().position(|u| u == upgrade);
+                (*cost, priority)
+            })?;
+        Some(Order::Upgrade {
+            station: busiest.entity,
+            upgrade: *upgrade,
+        })
+    }
+}
+
+impl Strategy for Greedy {
+    fn choose(&mut self, view: &View) -> Vec<Order> {
+        let mut free = view
+            .stations
+            .iter()
+            .map(|station| (station.entity, station.free_slots))
+            .collect::<HashMap<_, _>>();
+        let mut budget = free.values().sum::<usize>().saturating_sub(view.pending);
+
+        let mut options = Vec::new();
+        for present in view.presents.iter() {
+            for station in view.stations.iter() {
+                if present.reachable_from.contains(&station.entity) {
+                    let distance = station.position.distance(present.position);
+                    let value = present.kind.points() as f32 / (1.0 + distance);
+                    options.push((value, present.entity, station.entity));
+                }
+            }
+        }
+        options.sort_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+
+        let mut chosen = Vec::new();
+        let mut orders = Vec::new();
+        for (_, present, station) in options {
+            if budget == 0 {
+                break;
+            }
+            let slots = free.entry(station).or_default();
+            if *slots == 0 || chosen.contains(&present) {
+                continue;
+            }
+            *slots -= 1;
+            budget -= 1;
+            chosen.push(present);
+            orders.push(Order::Collect {
+                present,
+                station: Some(station),
+            });
+        }
+        orders.extend(Self::upgrade(view));
+        orders
+    }
+}
+
+/// Marks everything any rocket can reach and leaves the rest to the rockets. Useful as a
+/// baseline when comparing strategies
+pub struct MarkAll;
+
+impl Strategy for MarkAll {
+    fn choose(&mut self, view: &View) -> Vec<Order> {
+        view.presents
+            .iter()
+            .filter(|present| !present.reachable_from.is_empty())
+            .map(|present| Order::Collect {
+                present: present.entity,
+                station: None,
+            })
+            .collect()
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn play(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut auto_player: ResMut<AutoPlayer>,
+    present_query: Query<
+        (
+            Entity,
+            &Present,
+            &Orbit,
+            Option<&CollectPresent>,
+            Option<&RocketTarget>,
+        ),
+        Without<Cargo>,
+    >,
+    rocket_query: Query<&Rocket>,
+    station_query: Query<(Entity, &GroundStation, &Coord)>,
+    timer: Res<GameTimer>,
+    game_data: Res<GameData>,
+    selected_material: Option<Res<SelectedMaterial>>,
+    mut upgrade_events: EventWriter<UpgradeStation>,
+) {
+    if !auto_player.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let stations = station_query
+        .iter()
+        .map(|(entity, station, coord)| StationView {
+            entity,
+            position: coord.to_transform().translation,
+            upgrades: UPGRADE_PRIORITY
+                .iter()
+                .filter_map(|upgrade| Some((*upgrade, station.upgrade_cost(*upgrade)?)))
+                .collect(),
+            free_slots: rocket_query
+                .iter()
+                .filter(|rocket| rocket.station == entity && rocket.is_available())
+                .map(|rocket| {
+                    rocket
+                        .capacity
+                        .saturating_sub(rocket.route.len() + rocket.cargo.len())
+                })
+                .sum(),
+        })
+        .collect();
+
+    let mut pending = 0;
+    let mut presents = Vec::new();
+    for (entity, present, orbit, collect, target) in present_query.iter() {
+        if collect.is_some() {
+            if target.is_none() {
+                pending += 1;
+            }
+            continue;
+        }
+        let position = orbit.position();
+        let mut reachable_from = rocket_query
+            .iter()
+            .filter(|rocket| rocket.can_reach(position))
+            .map(|rocket| rocket.station)
+            .collect::<Vec<_>>();
+        reachable_from.sort();
+        reachable_from.dedup();
+        presents.push(PresentView {
+            entity,
+            kind: present.kind,
+            position,
+            reachable_from,
+        });
+    }
+
+    let view = View {
+        presents,
+        stations,
+        pending,
+        coins: game_data.coins,
+        time_left: timer.remaining_secs(),
+    };
+
+    for order in auto_player.strategy.choose(&view) {
+        match order {
+            Order::Collect { present, station } => {
+                let mut present = commands.entity(present);
+                present.insert(CollectPresent { station });
+                // Only there when playing live
+                if let Some(selected_material) = &selected_material {
+                    present.insert(selected_material.mat.clone());
+                }
+            }
+            Order::Upgrade { station, upgrade } => {
+                upgrade_events.send(UpgradeStation { station, upgrade })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn present(index: u32, kind: PresentKind, x: f32, stations: &[Entity]) -> PresentView {
+        PresentView {
+            entity: Entity::from_raw(index),
+            kind,
+            position: Vec3::new(x, 0.0, 0.0),
+            reachable_from: stations.to_vec(),
+        }
+    }
+
+    #[test]
+    fn greedy_fills_free_slots_with_the_closest_presents() {
+        let station = Entity::from_raw(100);
+        let view = View {
+            presents: vec![
+                present(1, PresentKind::Standard, 10.0, &[station]),
+                present(2, PresentKind::Standard, 2.0, &[station]),
+                present(3, PresentKind::Standard, 5.0, &[station]),
+            ],
+            stations: vec![StationView {
+                entity: station,
+                position: Vec3::ZERO,
+                upgrades: Vec::new(),
+                free_slots: 2,
+            }],
+            ..default()
+        };
+
+        let orders = Greedy.choose(&view);
+
+        assert_eq!(
+            orders,
+            vec![
+                Order::Collect {
+                    present: Entity::from_raw(2),
+                    station: Some(station)
+                },
+                Order::Collect {
+                    present: Entity::from_raw(3),
+                    station: Some(station)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn greedy_prefers_valuable_presents_and_skips_unreachable_ones() {
+        let station = Entity::from_raw(100);
+        let view = View {
+            presents: vec![
+                present(1, PresentKind::Standard, 3.0, &[station]),
+                present(2, PresentKind::Golden, 4.0, &[station]),
+                present(3, PresentKind::Golden, 1.0, &[]),
+            ],
+            stations: vec![StationView {
+                entity: station,
+                position: Vec3::ZERO,
+                upgrades: Vec::new(),
+                free_slots: 1,
+            }],
+            ..default()
+        };
+
+        let orders = Greedy.choose(&view);
+
+        assert_eq!(
+            orders,
+            vec![Order::Collect {
+                present: Entity::from_raw(2),
+                station: Some(station)
+            }]
+        );
+    }
+
+    #[test]
+    fn greedy_waits_while_marked_presents_are_pending() {
+        let station = Entity::from_raw(100);
+        let view = View {
+            presents: vec![present(1, PresentKind::Standard, 3.0, &[station])],
+            stations: vec![StationView {
+                entity: station,
+                position: Vec3::ZERO,
+                upgrades: Vec::new(),
+                free_slots: 2,
+            }],
+            pending: 2,
+            ..default()
+        };
+
+        assert!(Greedy.choose(&view).is_empty());
+    }
+
+    #[test]
+    fn greedy_buys_the_cheapest_safe_upgrade_for_the_busiest_station() {
+        let quiet = Entity::from_raw(100);
+        let busy = Entity::from_raw(101);
+        let upgrades = vec![
+            (Upgrade::Fleet, 10),
+            (Upgrade::Speed, 3),
+            (Upgrade::Cargo, 5),
+        ];
+        let view = View {
+            presents: vec![
+                present(1, PresentKind::Standard, 3.0, &[busy]),
+                present(2, PresentKind::Fragile, 4.0, &[busy, quiet]),
+            ],
+            stations: vec![
+                StationView {
+                    entity: quiet,
+                    position: Vec3::ZERO,
+                    free_slots: 0,
+                    upgrades: upgrades.clone(),
+                },
+                StationView {
+                    entity: busy,
+                    position: Vec3::ZERO,
+                    free_slots: 0,
+                    upgrades,
+                },
+            ],
+            coins: 7,
+            ..default()
+        };
+
+        assert_eq!(
+            Greedy.choose(&view),
+            vec![Order::Upgrade {
+                station: busy,
+                upgrade: Upgrade::Cargo
+            }]
+        );
+    }
+}
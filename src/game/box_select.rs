@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use leafwing_input_manager::action_state::ActionState;
+
+use crate::{coord::GROUND_DISTANCE, input::Pointer, state::InteractionState};
+
+use super::{
+    ground_station::SelectedStation,
+    present::{CollectPresent, Present, SelectedMaterial},
+    rocket::Cargo,
+    trajectory, PlayerAction,
+};
+
+const MIN_SIZE: f32 = 4.0; // Drags smaller than this in pixels are treated as a click
+
+/// Screen space rectangle being dragged out while holding shift
+#[derive(Resource, Default)]
+pub struct SelectionBox {
+    pub start: Option<Vec2>,
+    pub end: Vec2,
+}
+
+impl SelectionBox {
+    pub fn rect(&self) -> Option<Rect> {
+        self.start.map(|start| Rect::from_corners(start, self.end))
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn update(
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    action_query: Query<&ActionState<PlayerAction>>,
+    present_query: Query<
+        (Entity, &GlobalTransform),
+        (With<Present>, Without<CollectPresent>, Without<Cargo>),
+    >,
+    mut selection: ResMut<SelectionBox>,
+    selected_station: Res<SelectedStation>,
+    selected_material: Res<SelectedMaterial>,
+    mut next_interaction_state: ResMut<NextState<InteractionState>>,
+    pointer: Res<Pointer>,
+) {
+    let action = action_query.single();
+
+    if let Some(cursor_position) = pointer.position {
+        selection.end = cursor_position;
+
+        if action.pressed(PlayerAction::Select) && action.just_pressed(PlayerAction::CanMove) {
+            selection.start = Some(cursor_position);
+            next_interaction_state.set(InteractionState::Selecting);
+        }
+    }
+
+    if !action.just_released(PlayerAction::CanMove) {
+        return;
+    }
+
+    let Some(rect) = selection.rect() else {
+        return;
+    };
+    selection.start = None;
+
+    if rect.width() < MIN_SIZE && rect.height() < MIN_SIZE {
+        return;
+    }
+
+    for (camera, camera_transform) in &cameras {
+        let eye = camera_transform.translation();
+
+        for (entity, transform) in present_query.iter() {
+            let pos = transform.translation();
+
+            // Skip anything hidden behind the earth
+            if trajectory::segment_min_distance(eye, pos) < GROUND_DISTANCE {
+                continue;
+            }
+
+            let Some(screen_pos) = camera.world_to_viewport(camera_transform, pos) else {
+                continue;
+            };
+
+            if rect.contains(screen_pos) {
+                commands.entity(entity).insert((
+                    CollectPresent {
+                        station: selected_station.0,
+                    },
+                    selected_material.mat.clone(),
+                ));
+            }
+        }
+    }
+}
+
+pub fn clear(mut selection: ResMut<SelectionBox>) {
+    selection.start = None;
+}
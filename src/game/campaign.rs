@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+
+use crate::replay::PlayedBack;
+
+use super::{auto_player::AutoPlayer, score::Score, GameData};
+
+#[derive(Clone, Default)]
+pub struct LevelInfo {
+    pub name: &'static str,
+    pub path: &'static str,
+}
+
+// Levels in the order they are played. Each one is unlocked by winning the one before it
+pub const LEVELS: [LevelInfo; 3] = [
+    LevelInfo {
+        name: "Low Earth Orbit",
+        path: "levels/01.level.ron",
+    },
+    LevelInfo {
+        name: "Crowded Skies",
+        path: "levels/02.level.ron",
+    },
+    LevelInfo {
+        name: "Christmas Eve",
+        path: "levels/03.level.ron",
+    },
+];
+
+/// Best result on a level so far
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LevelRecord {
+    pub score: i32,
+    pub stars: usize,
+}
+
+#[derive(Resource)]
+pub struct Campaign {
+    pub current: usize,
+    pub unlocked: usize, // Number of levels that can be played
+    pub records: [LevelRecord; LEVELS.len()],
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        Self {
+            current: 0,
+            unlocked: 1,
+            records: Default::default(),
+        }
+    }
+}
+
+impl Campaign {
+    pub fn current_level(&self) -> &'static LevelInfo {
+        &LEVELS[self.current]
+    }
+
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        index < self.unlocked.min(LEVELS.len())
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.is_unlocked(self.current + 1)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if self.is_unlocked(index) {
+            self.current = index;
+        } else {
+            warn!("Level {} is locked", index);
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.select(self.current + 1);
+    }
+
+    /// Keeps the best score and stars for the current level
+    pub fn record(&mut self, score: i32, stars: usize) {
+        let record = &mut self.records[self.current];
+        record.score = record.score.max(score);
+        record.stars = record.stars.max(stars);
+    }
+
+    pub fn unlock_next(&mut self) {
+        self.unlocked = self.unlocked.max(self.current + 2).min(LEVELS.len());
+    }
+}
+
+pub fn complete_level(
+    game_data: Res<GameData>,
+    score: Res<Score>,
+    mut campaign: ResMut<Campaign>,
+    auto_player: Option<Res<AutoPlayer>>,
+    played_back: Option<Res<PlayedBack>>,
+) {
+    // Games played by the computer or someone else's replay dont count
+    if auto_player.is_some() || played_back.is_some() {
+        return;
+    }
+
+    campaign.record(score.total(), score.stars);
+
+    if game_data.won {
+        campaign.unlock_next();
+        info!("Completed level {}", campaign.current_level().name);
+    }
+}
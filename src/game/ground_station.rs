@@ -1,8 +1,10 @@
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{asset::LoadingAssets, Coord};
 
-use super::{rocket::Rocket, GameElement};
+use super::{interpolation::Interpolated, rocket::Rocket, GameData, GameElement, LevelConfig};
 
 #[derive(Clone, Default)]
 pub struct Info {
@@ -30,7 +32,7 @@ pub enum Country {
 // Pulled from https://en.wikipedia.org/wiki/List_of_rocket_launch_sites
 // South and West are negative
 #[allow(clippy::excessive_precision)]
-const STATIONS: [Info; 10] = [
+pub const STATIONS: [Info; 10] = [
     Info {
         coord: Vec2::new(-30.95875, 136.50366),
         name: "Woomera Test Range",
@@ -83,10 +85,78 @@ const STATIONS: [Info; 10] = [
     },
 ];
 
+// Stats for each upgrade tier. Everything starts on the first tier
+const FLEET_TIERS: [usize; 4] = [1, 2, 3, 4];
+const SPEED_TIERS: [f32; 4] = [10.0, 12.5, 15.0, 18.0];
+const CARGO_TIERS: [usize; 4] = [4, 5, 6, 8];
+const UPGRADE_COSTS: [i32; 3] = [5, 10, 20]; // Cost to reach the next tier
+
+// Where extra rockets sit on the pad relative to the center
+const PAD_SLOTS: [Vec2; 4] = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.35, 0.0),
+    Vec2::new(-0.35, 0.0),
+    Vec2::new(0.0, 0.35),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Upgrade {
+    Fleet,
+    Speed,
+    Cargo,
+}
+
 #[derive(Component, Default)]
-pub struct GroundStation {}
+pub struct GroundStation {
+    pub name: &'static str,
+    pub fleet_tier: usize,
+    pub speed_tier: usize,
+    pub cargo_tier: usize,
+}
+
+impl GroundStation {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, ..default() }
+    }
+
+    pub fn tier(&self, upgrade: Upgrade) -> usize {
+        match upgrade {
+            Upgrade::Fleet => self.fleet_tier,
+            Upgrade::Speed => self.speed_tier,
+            Upgrade::Cargo => self.cargo_tier,
+        }
+    }
+
+    /// Cost of the next tier. None once fully upgraded
+    pub fn upgrade_cost(&self, upgrade: Upgrade) -> Option<i32> {
+        UPGRADE_COSTS.get(self.tier(upgrade)).copied()
+    }
+
+    pub fn fleet_size(&self) -> usize {
+        FLEET_TIERS[self.fleet_tier]
+    }
+
+    pub fn rocket_speed(&self) -> f32 {
+        SPEED_TIERS[self.speed_tier]
+    }
 
-#[derive(Resource)]
+    pub fn rocket_capacity(&self) -> usize {
+        CARGO_TIERS[self.cargo_tier]
+    }
+}
+
+/// Station picked by clicking its launch pad
+#[derive(Resource, Default)]
+pub struct SelectedStation(pub Option<Entity>);
+
+#[derive(Event)]
+pub struct UpgradeStation {
+    pub station: Entity,
+    pub upgrade: Upgrade,
+}
+
+// Models are only loaded by the scene plugin so these stay empty when simulated
+#[derive(Resource, Default)]
 pub struct StationResources {
     pub pad: Handle<Scene>,
     pub rocket: Handle<Scene>,
@@ -104,55 +174,148 @@ pub fn load_resources(
     commands.insert_resource(StationResources { pad, rocket });
 }
 
-pub fn spawn(mut commands: Commands, scene_res: Res<StationResources>) {
+pub fn spawn(
+    mut commands: Commands,
+    scene_res: Res<StationResources>,
+    level_config: Res<LevelConfig>,
+) {
     for station in STATIONS {
-        spawn_station(&mut commands, station, &scene_res);
+        if level_config.is_station_active(station.name) {
+            spawn_station(&mut commands, station, &scene_res);
+        }
     }
 }
 
 fn spawn_station(commands: &mut Commands, station: Info, scene_res: &Res<StationResources>) {
     let coord = Coord::from_degrees(station.coord);
 
-    let rocket_scene = SceneBundle {
-        scene: scene_res.rocket.clone_weak(),
-        transform: Transform::from_scale(Vec3::new(0.2, 0.2, 0.2)),
+    let pad_scene = SceneBundle {
+        scene: scene_res.pad.clone_weak(),
+        transform: Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians()))
+            .with_scale(Vec3::new(0.2, 0.2, 0.2)),
         ..default()
     };
-    let rocket_entity = commands.spawn(rocket_scene).id();
-    commands
+    let pad_entity = commands.spawn((pad_scene, GameElement)).id();
+
+    let ground_station = GroundStation::new(station.name);
+    let fleet_size = ground_station.fleet_size();
+    let speed = ground_station.rocket_speed();
+    let capacity = ground_station.rocket_capacity();
+
+    let station_entity = commands
         .spawn((
-            Rocket::default(),
+            ground_station,
+            coord,
             TransformBundle {
-                local: coord.to_transform()
-                    * Transform::from_rotation(Quat::from_rotation_x(180f32.to_radians()))
-                    * Transform::from_translation(Vec3::new(0.0, 0.0, -0.5)),
+                local: coord.to_transform(),
                 global: GlobalTransform::IDENTITY,
             },
             Visibility::Visible,
             InheritedVisibility::VISIBLE,
+            RigidBody::Fixed,
+            Collider::ball(0.6), // So the pad can be clicked
             GameElement,
         ))
-        .push_children(&[rocket_entity]);
+        .push_children(&[pad_entity])
+        .id();
 
-    let pad_scene = SceneBundle {
-        scene: scene_res.pad.clone_weak(),
-        transform: Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians()))
-            .with_scale(Vec3::new(0.2, 0.2, 0.2)),
+    for slot in 0..fleet_size {
+        spawn_rocket(
+            commands,
+            station_entity,
+            coord,
+            slot,
+            speed,
+            capacity,
+            scene_res,
+        );
+    }
+}
+
+fn spawn_rocket(
+    commands: &mut Commands,
+    station: Entity,
+    coord: Coord,
+    slot: usize,
+    speed: f32,
+    capacity: usize,
+    scene_res: &StationResources,
+) {
+    let rocket_scene = SceneBundle {
+        scene: scene_res.rocket.clone_weak(),
+        transform: Transform::from_scale(Vec3::new(0.2, 0.2, 0.2)),
         ..default()
     };
-    let pad_entity = commands.spawn((pad_scene, GameElement)).id();
-
+    let rocket_entity = commands.spawn(rocket_scene).id();
+    let offset = PAD_SLOTS[slot % PAD_SLOTS.len()];
+    let home = coord.to_transform()
+        * Transform::from_translation(offset.extend(0.0))
+        * Transform::from_rotation(Quat::from_rotation_x(180f32.to_radians()))
+        * Transform::from_translation(Vec3::new(0.0, 0.0, -0.5));
     commands
         .spawn((
-            GroundStation::default(),
-            coord,
+            Rocket::new(station, home, speed, capacity),
             TransformBundle {
-                local: coord.to_transform(),
+                local: home,
                 global: GlobalTransform::IDENTITY,
             },
+            Interpolated::new(home),
             Visibility::Visible,
             InheritedVisibility::VISIBLE,
             GameElement,
         ))
-        .push_children(&[pad_entity]);
+        .push_children(&[rocket_entity]);
+}
+
+pub fn apply_upgrades(
+    mut commands: Commands,
+    mut events: EventReader<UpgradeStation>,
+    mut station_query: Query<(&mut GroundStation, &Coord)>,
+    mut rocket_query: Query<&mut Rocket>,
+    mut game_data: ResMut<GameData>,
+    scene_res: Res<StationResources>,
+) {
+    for event in events.read() {
+        let Ok((mut station, coord)) = station_query.get_mut(event.station) else {
+            continue;
+        };
+        let Some(cost) = station.upgrade_cost(event.upgrade) else {
+            continue; // Already maxed out
+        };
+        if game_data.coins < cost {
+            continue;
+        }
+        game_data.coins -= cost;
+
+        match event.upgrade {
+            Upgrade::Fleet => station.fleet_tier += 1,
+            Upgrade::Speed => station.speed_tier += 1,
+            Upgrade::Cargo => station.cargo_tier += 1,
+        }
+        info!("Upgraded {:?} at {}", event.upgrade, station.name);
+
+        let mut fleet = 0;
+        for mut rocket in rocket_query.iter_mut() {
+            if rocket.station == event.station {
+                rocket.speed = station.rocket_speed();
+                rocket.capacity = station.rocket_capacity();
+                fleet += 1;
+            }
+        }
+        for slot in fleet..station.fleet_size() {
+            spawn_rocket(
+                &mut commands,
+                event.station,
+                *coord,
+                slot,
+                station.rocket_speed(),
+                station.rocket_capacity(),
+                &scene_res,
+            );
+        }
+    }
+}
+
+pub fn clear_selection(mut selected: ResMut<SelectedStation>) {
+    selected.0 = None;
 }
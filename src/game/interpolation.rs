@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+/// Smooths out something moved by the fixed step gameplay. `Transform` only holds where it
+/// really is while a step runs. The rest of the frame it is drawn part way between the last
+/// two steps
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Interpolated {
+    previous: Transform,
+    current: Transform,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+
+    /// Between the last two steps, `t` being how far from the previous one to go
+    pub fn at(&self, t: f32) -> Transform {
+        Transform {
+            translation: self.previous.translation.lerp(self.current.translation, t),
+            rotation: self.previous.rotation.slerp(self.current.rotation, t),
+            scale: self.previous.scale.lerp(self.current.scale, t),
+        }
+    }
+}
+
+// Puts things back where they really are before a step moves them
+pub fn restore(mut query: Query<(&mut Interpolated, &mut Transform)>) {
+    for (mut interpolated, mut transform) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        *transform = interpolated.current;
+    }
+}
+
+// Remembers where a step left things
+pub fn store(mut query: Query<(&mut Interpolated, &Transform)>) {
+    for (mut interpolated, transform) in query.iter_mut() {
+        interpolated.current = *transform;
+    }
+}
+
+// Draws things as far between steps as the time left over from the last step
+pub fn interpolate(time: Res<Time<Fixed>>, mut query: Query<(&Interpolated, &mut Transform)>) {
+    let t = time.overstep_percentage();
+    for (interpolated, mut transform) in query.iter_mut() {
+        *transform = interpolated.at(t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawn_between_the_last_two_steps() {
+        let mut interpolated = Interpolated::new(Transform::from_xyz(0.0, 0.0, 0.0));
+        interpolated.previous = interpolated.current;
+        interpolated.current = Transform::from_xyz(4.0, 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2));
+
+        let halfway = interpolated.at(0.5);
+        assert_eq!(halfway.translation, Vec3::new(2.0, 0.0, 0.0));
+        assert!(halfway
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4), 1e-6));
+        assert_eq!(interpolated.at(1.0), interpolated.current);
+    }
+}
@@ -0,0 +1,304 @@
+use std::ops::Range;
+
+use bevy::utils::thiserror;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::{asset::LoadingAssets, coord::GROUND_DISTANCE};
+
+use super::{
+    campaign::Campaign, ground_station::STATIONS, present::PresentKind, spawn_pattern::SpawnPattern,
+};
+
+/// Level description loaded from a `.level.ron` file under `assets/levels/`
+#[derive(Asset, Resource, TypePath, Debug, Clone, Deserialize)]
+pub struct LevelConfig {
+    pub time: i32,
+    pub bands: Vec<OrbitBand>,
+    // Names of the stations from `ground_station::STATIONS` to use. All of them if not set
+    #[serde(default)]
+    pub stations: Option<Vec<String>>,
+    // Fixed seed for the present layout. Random every game if not set
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // How common each kind of present is. Only standard presents if not set
+    #[serde(default)]
+    pub presents: PresentMix,
+    // Scores needed for one, two and three stars. Worked out from the presents if not set
+    #[serde(default)]
+    pub stars: Option<[i32; 3]>,
+}
+
+/// Relative weights used to pick the kind of each present
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PresentMix {
+    pub standard: f32,
+    pub fragile: f32,
+    pub heavy: f32,
+    pub golden: f32,
+}
+
+impl Default for PresentMix {
+    fn default() -> Self {
+        Self {
+            standard: 1.0,
+            fragile: 0.0,
+            heavy: 0.0,
+            golden: 0.0,
+        }
+    }
+}
+
+impl PresentMix {
+    pub fn weight(&self, kind: PresentKind) -> f32 {
+        match kind {
+            PresentKind::Standard => self.standard,
+            PresentKind::Fragile => self.fragile,
+            PresentKind::Heavy => self.heavy,
+            PresentKind::Golden => self.golden,
+        }
+    }
+}
+
+/// A layer of presents spawned between two altitudes
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrbitBand {
+    pub count: i32,
+    pub altitude: Range<f32>,
+    pub present_size: f32,
+    pub present_hitbox_size: f32,
+    #[serde(deserialize_with = "deserialize_colors")]
+    pub colors: Vec<Color>,
+    // Where around the earth the presents are. Evenly everywhere if not set
+    #[serde(default)]
+    pub pattern: SpawnPattern,
+}
+
+impl LevelConfig {
+    pub fn total_presents(&self) -> i32 {
+        self.bands.iter().map(|band| band.count).sum()
+    }
+
+    pub fn is_station_active(&self, name: &str) -> bool {
+        match &self.stations {
+            Some(stations) => stations.iter().any(|s| s == name),
+            None => true,
+        }
+    }
+
+    pub fn star_thresholds(&self) -> [i32; 3] {
+        self.stars.unwrap_or_else(|| {
+            // Based on the points from collecting everything with no combos or bonuses
+            let total_weight = PresentKind::ALL
+                .iter()
+                .map(|kind| self.presents.weight(*kind))
+                .sum::<f32>();
+            let average_points = PresentKind::ALL
+                .iter()
+                .map(|kind| kind.points() as f32 * self.presents.weight(*kind))
+                .sum::<f32>()
+                / total_weight;
+            let full = self.total_presents() as f32 * average_points;
+            [0.4, 0.7, 1.0].map(|fraction| (full * fraction).round() as i32)
+        })
+    }
+
+    fn validate(&self) -> Result<(), LevelLoaderError> {
+        if self.time <= 0 {
+            return Err(LevelLoaderError::Invalid("time must be positive".into()));
+        }
+        // A level with nothing to collect would be won as soon as it started
+        if self.total_presents() <= 0 {
+            return Err(LevelLoaderError::Invalid("level has no presents".into()));
+        }
+
+        for band in self.bands.iter() {
+            if band.count < 0 {
+                return Err(LevelLoaderError::Invalid("band count is negative".into()));
+            }
+            if band.altitude.is_empty() {
+                return Err(LevelLoaderError::Invalid(format!(
+                    "band altitude {:?} is empty",
+                    band.altitude
+                )));
+            }
+            if band.altitude.start <= GROUND_DISTANCE {
+                return Err(LevelLoaderError::Invalid(format!(
+                    "band altitude {:?} is not above the ground at {}",
+                    band.altitude, GROUND_DISTANCE
+                )));
+            }
+            if band.present_size <= 0.0 || band.present_hitbox_size <= 0.0 {
+                return Err(LevelLoaderError::Invalid(
+                    "band present sizes must be positive".into(),
+                ));
+            }
+            if band.colors.is_empty() {
+                return Err(LevelLoaderError::Invalid("band has no colors".into()));
+            }
+            if !band.pattern.is_valid() {
+                return Err(LevelLoaderError::Invalid(format!(
+                    "band pattern {:?} has a negative spread or length",
+                    band.pattern
+                )));
+            }
+        }
+
+        let weights = PresentKind::ALL.map(|kind| self.presents.weight(kind));
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(LevelLoaderError::Invalid(
+                "present weights must not be negative".into(),
+            ));
+        }
+        if weights.iter().sum::<f32>() <= 0.0 {
+            return Err(LevelLoaderError::Invalid(
+                "at least one kind of present needs a weight".into(),
+            ));
+        }
+
+        if let Some(stars) = self.stars {
+            if stars[0] < 0 || stars[0] > stars[1] || stars[1] > stars[2] {
+                return Err(LevelLoaderError::Invalid(format!(
+                    "star thresholds {:?} must go up",
+                    stars
+                )));
+            }
+        }
+
+        if let Some(stations) = &self.stations {
+            if stations.is_empty() {
+                return Err(LevelLoaderError::Invalid(
+                    "level has no stations to launch rockets from".into(),
+                ));
+            }
+            for name in stations {
+                if !STATIONS.iter().any(|s| s.name == name) {
+                    return Err(LevelLoaderError::Invalid(format!(
+                        "unknown station {}",
+                        name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Colors are written as hex strings ("#e01b24") so they are easy to edit by hand
+fn deserialize_colors<'de, D>(deserializer: D) -> Result<Vec<Color>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = Vec::<String>::deserialize(deserializer)?;
+    hex.iter()
+        .map(|h| Color::hex(h).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LevelLoaderError {
+    #[error("Could not load level: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse level: {0}")]
+    RonSpannedError(#[from] ron::error::SpannedError),
+    #[error("Invalid level: {0}")]
+    Invalid(String),
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = LevelConfig;
+    type Settings = ();
+    type Error = LevelLoaderError;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let level = ron::de::from_bytes::<LevelConfig>(&bytes)?;
+            level.validate()?;
+            Ok(level)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelResources {
+    pub level: Handle<LevelConfig>,
+}
+
+pub fn load_resources(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<LoadingAssets>,
+    campaign: Res<Campaign>,
+) {
+    let level = asset_server.load(campaign.current_level().path);
+    loading.0.push(level.id().into());
+    commands.insert_resource(LevelResources { level });
+}
+
+// Copy the loaded level into a resource so the game systems dont need to look it up
+pub fn setup(
+    mut commands: Commands,
+    level_res: Res<LevelResources>,
+    levels: Res<Assets<LevelConfig>>,
+) {
+    match levels.get(&level_res.level) {
+        Some(level) => commands.insert_resource(level.clone()),
+        None => error!("Level {:?} was not loaded", level_res.level.path()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAND: &str = "(count: 10, altitude: (start: 21.0, end: 24.0), present_size: 0.4, \
+                        present_hitbox_size: 0.5, colors: [\"#e01b24\"])";
+
+    fn validate(text: &str) -> Result<(), LevelLoaderError> {
+        ron::from_str::<LevelConfig>(text).unwrap().validate()
+    }
+
+    #[test]
+    fn playable_level_is_valid() {
+        assert!(validate(&format!("(time: 60, bands: [{}])", BAND)).is_ok());
+    }
+
+    #[test]
+    fn unplayable_levels_are_invalid() {
+        for level in [
+            "(time: 60, bands: [])".to_string(),
+            format!("(time: 60, bands: [{}], stations: Some([]))", BAND),
+            format!("(time: 60, bands: [{}])", BAND.replace("21.0", "20.0")),
+            format!("(time: 60, bands: [{}])", BAND.replace("0.4", "0.0")),
+            format!("(time: 60, bands: [{}])", BAND.replace("0.5", "-1.0")),
+        ] {
+            assert!(
+                matches!(validate(&level), Err(LevelLoaderError::Invalid(_))),
+                "{} should be invalid",
+                level
+            );
+        }
+    }
+}
@@ -1,44 +1,66 @@
-use bevy::prelude::*;
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState},
+    prelude::*,
+    transform::TransformSystem,
+};
+use bevy_rapier3d::plugin::PhysicsSet;
 use leafwing_input_manager::Actionlike;
+use serde::{Deserialize, Serialize};
 
+pub mod auto_player;
+pub mod box_select;
+pub mod campaign;
 pub mod player;
 pub mod present;
 pub use player::Player;
 pub mod ground_station;
 pub use ground_station::GroundStation;
+pub mod interpolation;
+pub mod level;
+pub use level::LevelConfig;
+pub mod orbit;
 pub mod rocket;
+pub mod routing;
+pub mod score;
+pub mod seed;
+pub mod selection;
+pub mod spawn_pattern;
+pub use seed::{GameRng, GameSeed};
+pub mod trajectory;
 
 use crate::{
-    asset::LoadingAssets,
-    despawn,
+    asset::{LoadError, LoadingAssets},
+    despawn, scene,
     state::{AppState, GameState},
 };
 
 use self::present::Present;
 
-#[derive(Resource)]
-pub struct LevelConfig {
-    pub low_orbit_presents: i32,
-    pub high_orbit_presents: i32,
-    pub time: i32,
-    pub present_size: f32,
-    pub present_hitbox_size: f32,
-}
-
 #[derive(Resource, Default)]
 pub struct GameData {
     pub presents_collected: i32,
+    pub presents_broken: i32,
     pub won: bool,
+    pub coins: i32, // Earned by delivering presents and spent on station upgrades
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Actionlike)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Actionlike, Serialize, Deserialize)]
 pub enum PlayerAction {
     CanMove,
     Move,
     Zoom,
     Pause,
+    CycleStation,
+    Select,
+    OrbitAxis, // Held inputs like sticks that turn the camera without dragging
+    ZoomAxis,  // Held inputs like triggers that zoom while held
+    Aim,       // Moves the reticle used in place of the mouse cursor
+    ResetCamera,
+    Focus, // Flies the camera to whatever is under the cursor
 }
 
+const TICK_RATE: f64 = 60.0; // Gameplay steps per second, whatever the frame rate
+
 #[derive(Component)]
 struct GameElement;
 
@@ -49,44 +71,113 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(Startup, present::init)
-            .add_systems(OnEnter(AppState::Loading), ground_station::load_resources)
+        app.init_asset::<LevelConfig>()
+            .init_asset_loader::<level::LevelLoader>()
+            .add_systems(
+                OnEnter(AppState::Loading),
+                (level::load_resources, clear_load_error),
+            )
+            .add_systems(
+                OnExit(AppState::Loading),
+                level::setup.run_if(not(resource_exists::<LoadError>())),
+            )
             .add_systems(
                 OnEnter(AppState::InGame),
-                (init, player::spawn, present::spawn, ground_station::spawn)
+                (
+                    init,
+                    seed::init.before(present::spawn),
+                    score::init,
+                    player::spawn,
+                    present::spawn,
+                    ground_station::spawn,
+                )
                     .run_if(in_state(AppState::InGame)),
             )
+            // Gameplay moves in steps of the same length so it plays out the same at any frame
+            // rate, and all of it stops while paused. Input and upgrades still happen once a
+            // frame
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
-                    present::cast_ray.run_if(in_state(AppState::InGame)),
-                    rocket::collect_presents.run_if(in_state(AppState::InGame)),
-                    rocket::update_target.run_if(in_state(AppState::InGame)),
-                    update_present_count.run_if(in_state(AppState::InGame)),
-                ),
+                    interpolation::restore,
+                    (
+                        rocket::collect_presents,
+                        orbit::update,
+                        rocket::update_target,
+                        score::update,
+                        update_present_count,
+                        countdown,
+                    )
+                        .chain()
+                        .run_if(in_state(AppState::InGame))
+                        .run_if(in_state(GameState::Running))
+                        .run_if(level_running),
+                    interpolation::store,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                ground_station::apply_upgrades.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                PostUpdate,
+                interpolation::interpolate
+                    .before(PhysicsSet::SyncBackend)
+                    .before(TransformSystem::TransformPropagate),
             )
             .add_systems(
                 Update,
                 check_assets_loaded.run_if(in_state(AppState::Loading)),
             )
-            .add_systems(OnExit(AppState::InGame), despawn::<GameElement>)
-            .add_systems(Update, countdown.run_if(in_state(AppState::InGame)))
             .add_systems(
-                OnEnter(GameState::Paused),
-                pause.run_if(in_state(AppState::InGame)),
+                OnExit(AppState::InGame),
+                (despawn::<GameElement>, ground_station::clear_selection),
+            )
+            .add_systems(
+                OnEnter(AppState::PostGame),
+                (score::finish, campaign::complete_level).chain(),
             )
+            .add_systems(Update, scene::update_coords)
+            .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+            .insert_resource(GameData::default())
+            .init_resource::<campaign::Campaign>()
+            .init_resource::<GameSeed>()
+            .init_resource::<GameRng>()
+            .init_resource::<score::Score>()
+            .init_resource::<ground_station::StationResources>()
+            .add_event::<rocket::PresentRefused>()
+            .add_event::<rocket::PresentBroken>()
+            .add_event::<score::PresentPickedUp>()
+            .add_event::<ground_station::UpgradeStation>()
+            .init_resource::<ground_station::SelectedStation>();
+    }
+}
+
+// Systems for the player pointing at and marking things on screen. These need a window and a
+// camera so are left out when the game is simulated
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(Startup, (present::init, selection::init))
             .add_systems(
-                OnExit(GameState::Paused),
-                unpause.run_if(in_state(AppState::InGame)),
+                Update,
+                (
+                    present::cast_ray.run_if(in_state(AppState::InGame)),
+                    selection::cycle_station.run_if(in_state(AppState::InGame)),
+                    box_select::update
+                        .run_if(in_state(AppState::InGame))
+                        .run_if(in_state(GameState::Running)),
+                    selection::update_marker
+                        .after(selection::cycle_station)
+                        .after(present::cast_ray)
+                        .run_if(in_state(AppState::InGame)),
+                    selection::highlight_targets.run_if(in_state(AppState::InGame)),
+                ),
             )
-            .insert_resource(LevelConfig {
-                low_orbit_presents: 140,
-                high_orbit_presents: 30,
-                time: 160,
-                present_size: 0.4,
-                present_hitbox_size: 0.5,
-            })
-            .insert_resource(GameData::default());
+            .add_systems(OnExit(AppState::InGame), box_select::clear)
+            .init_resource::<box_select::SelectionBox>();
     }
 }
 
@@ -98,6 +189,21 @@ fn check_assets_loaded(
 ) {
     let mut loaded = 0;
     for id in loading.0.iter() {
+        // A broken level would never finish loading so give up and go back to the menu
+        if server.load_state(*id) == LoadState::Failed
+            || server.get_recursive_dependency_load_state(*id)
+                == Some(RecursiveDependencyLoadState::Failed)
+        {
+            let message = match server.get_path(*id) {
+                Some(path) => format!("Could not load {}", path),
+                None => "Could not load the level".into(),
+            };
+            error!("{}", message);
+            commands.insert_resource(LoadError(message));
+            commands.remove_resource::<LoadingAssets>();
+            game_state.set(AppState::StartMenu);
+            return;
+        }
         if server.is_loaded_with_dependencies(*id) {
             loaded += 1;
         }
@@ -110,14 +216,33 @@ fn check_assets_loaded(
     }
 }
 
-fn init(mut commands: Commands, level_config: Res<LevelConfig>, mut game_data: ResMut<GameData>) {
+fn clear_load_error(mut commands: Commands) {
+    commands.remove_resource::<LoadError>();
+}
+
+fn init(
+    mut commands: Commands,
+    level_config: Res<LevelConfig>,
+    mut game_data: ResMut<GameData>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    // Count steps from the start of the level so a replay steps at the same moments
+    *fixed_time = Time::<Fixed>::from_duration(fixed_time.timestep());
+
     commands.insert_resource(GameTimer(Timer::from_seconds(
         level_config.time as f32,
         TimerMode::Once,
     )));
 
     game_data.presents_collected = 0;
+    game_data.presents_broken = 0;
     game_data.won = false;
+    game_data.coins = 0;
+}
+
+// Nothing moves once the level is over, even if more steps are due before it ends
+fn level_running(timer: Option<Res<GameTimer>>, game_data: Res<GameData>) -> bool {
+    timer.is_some_and(|timer| !timer.finished()) && !game_data.won
 }
 
 fn countdown(
@@ -130,25 +255,19 @@ fn countdown(
     }
 }
 
-fn pause(mut timer: ResMut<GameTimer>) {
-    timer.pause();
-}
-
-fn unpause(mut timer: ResMut<GameTimer>) {
-    timer.unpause();
-}
-
 fn update_present_count(
     present_query: Query<&Present>,
     level_config: Res<LevelConfig>,
     mut game_data: ResMut<GameData>,
     mut app_state: ResMut<NextState<AppState>>,
 ) {
-    let target = level_config.high_orbit_presents + level_config.low_orbit_presents;
-    game_data.presents_collected = (target - present_query.iter().len() as i32).clamp(0, target);
+    let target = level_config.total_presents();
+    let remaining = present_query.iter().len() as i32;
+    game_data.presents_collected =
+        (target - remaining - game_data.presents_broken).clamp(0, target);
 
-    // Did we win??
-    if game_data.presents_collected >= target {
+    // Did we win?? Broken presents are lost but dont stop the sky from being cleared
+    if remaining == 0 {
         app_state.set(AppState::PostGame);
         game_data.won = true;
     }
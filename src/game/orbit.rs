@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use crate::{coord::CoordDistance, Coord};
+
+// Square root of the gravitational parameter of our tiny earth. Picked so low orbits do a lap
+// in about a minute and high orbits are noticeably slower
+const ORBIT_SPEED: f32 = 10.0;
+
+/// Circular orbit around the center of the earth
+#[derive(Component, Default, Copy, Clone, Debug)]
+pub struct Orbit {
+    pub inclination: f32,      // Tilt of the orbit plane from the equator in radians
+    pub ascending_node: f32,   // Rotation in radians of the orbit plane around the global Y axis
+    pub altitude: f32,         // Distance from the center of the earth
+    pub phase: f32,            // Angle in radians travelled along the orbit from the ascending node
+    pub angular_velocity: f32, // Radians per second
+}
+
+impl Orbit {
+    pub fn new(altitude: f32, inclination: f32, ascending_node: f32, phase: f32) -> Self {
+        Self {
+            inclination,
+            ascending_node,
+            altitude,
+            phase,
+            angular_velocity: ORBIT_SPEED / altitude.powf(1.5),
+        }
+    }
+
+    /// Orbit whose plane faces `normal`, starting `phase` radians from the ascending node
+    pub fn with_normal(altitude: f32, normal: Vec3, phase: f32) -> Self {
+        let normal = normal.normalize();
+        Self::new(
+            altitude,
+            normal.y.clamp(-1.0, 1.0).acos(),
+            normal.x.atan2(normal.z),
+            phase,
+        )
+    }
+
+    /// Orbit that is over `direction` right now and moving around `normal`. The two should be
+    /// at right angles
+    pub fn through(altitude: f32, direction: Vec3, normal: Vec3) -> Self {
+        let mut orbit = Self::with_normal(altitude, normal, 0.0);
+        let local = orbit.plane().inverse() * direction;
+        orbit.phase = (-local.z).atan2(local.x);
+        orbit
+    }
+
+    // Turns the equator onto the orbit plane
+    fn plane(&self) -> Quat {
+        Quat::from_rotation_y(self.ascending_node) * Quat::from_rotation_x(self.inclination)
+    }
+
+    /// Axis the orbit goes around
+    pub fn normal(&self) -> Vec3 {
+        self.plane() * Vec3::Y
+    }
+
+    pub fn advance(&mut self, secs: f32) {
+        self.phase = (self.phase + self.angular_velocity * secs) % std::f32::consts::TAU;
+    }
+
+    /// Position along the orbit `secs` seconds from now
+    pub fn position_in(&self, secs: f32) -> Vec3 {
+        let phase = self.phase + self.angular_velocity * secs;
+        self.plane() * Quat::from_rotation_y(phase) * Vec3::new(self.altitude, 0.0, 0.0)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position_in(0.0)
+    }
+
+    pub fn to_coord(&self) -> Coord {
+        let pos = self.position();
+        Coord {
+            long: pos.x.atan2(pos.z),
+            lat: (pos.y / self.altitude).clamp(-1.0, 1.0).asin(),
+            dist: CoordDistance::Orbit(self.altitude),
+        }
+    }
+
+    /// Where something should aim to meet this orbit given how long it takes to fly to a point
+    pub fn intercept(&self, flight_time: impl Fn(Vec3) -> f32) -> Vec3 {
+        // Refine the flight time a few times. Presents are much slower than rockets so this
+        // settles quickly
+        let mut target = self.position();
+        for _ in 0..4 {
+            target = self.position_in(flight_time(target));
+        }
+        target
+    }
+}
+
+pub fn update(time: Res<Time>, mut query: Query<(&mut Orbit, &mut Coord, &mut Transform)>) {
+    for (mut orbit, mut coord, mut transform) in query.iter_mut() {
+        orbit.advance(time.delta_seconds());
+        *coord = orbit.to_coord();
+        coord.apply(&mut transform);
+    }
+}
@@ -1,31 +1,97 @@
-use std::f32::consts::PI;
-
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
-use rand::{distributions::uniform::SampleRange, Rng};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+
+use crate::input::Pointer;
+use crate::state::InteractionState;
+
+use super::{
+    ground_station::SelectedStation, interpolation::Interpolated, orbit::Orbit, GameElement,
+    GameRng, GroundStation, LevelConfig, PlayerAction,
+};
+
+const FRAGILE_SPEED: f32 = 12.0; // Rockets faster than this break fragile presents
+const HEAVY_SCALE: f32 = 1.5;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub enum PresentKind {
+    #[default]
+    Standard,
+    Fragile, // Breaks if picked up by a fast rocket
+    Heavy,   // Takes up two cargo spots
+    Golden,  // Worth a lot but orbits quickly
+}
 
-use crate::{coord::CoordDistance, state::InteractionState, Coord};
+impl PresentKind {
+    pub const ALL: [PresentKind; 4] = [
+        PresentKind::Standard,
+        PresentKind::Fragile,
+        PresentKind::Heavy,
+        PresentKind::Golden,
+    ];
 
-use super::{GameElement, LevelConfig, PlayerAction};
+    /// Coins earned when delivered
+    pub fn value(&self) -> i32 {
+        match self {
+            PresentKind::Standard => 1,
+            PresentKind::Fragile => 2,
+            PresentKind::Heavy => 2,
+            PresentKind::Golden => 5,
+        }
+    }
 
-const PRESENT_COLORS: [Color; 5] = [
-    Color::rgb(0.878, 0.106, 0.141),
-    Color::rgb(0.20, 0.82, 0.478),
-    Color::rgb(0.208, 0.518, 0.894),
-    Color::rgb(0.965, 0.827, 0.176),
-    Color::rgb(0.569, 0.255, 0.675),
-];
+    /// Score for picking it up before any combo multiplier
+    pub fn points(&self) -> i32 {
+        match self {
+            PresentKind::Standard => 100,
+            PresentKind::Fragile => 150,
+            PresentKind::Heavy => 200,
+            PresentKind::Golden => 500,
+        }
+    }
+
+    /// Cargo spots used on a rocket
+    pub fn cargo_size(&self) -> usize {
+        match self {
+            PresentKind::Heavy => 2,
+            _ => 1,
+        }
+    }
+
+    /// Can a rocket going this fast pick it up without breaking it
+    pub fn survives_pickup(&self, speed: f32) -> bool {
+        match self {
+            PresentKind::Fragile => speed <= FRAGILE_SPEED,
+            _ => true,
+        }
+    }
+
+    fn orbit_speed(&self) -> f32 {
+        match self {
+            PresentKind::Golden => 1.5,
+            _ => 1.0,
+        }
+    }
+}
 
 #[derive(Component, Default)]
-pub struct Present {}
+pub struct Present {
+    pub id: usize, // Spawn order, which is the same every time a seed is played
+    pub kind: PresentKind,
+    pub material: Handle<StandardMaterial>, // Material to use when not selected
+}
 
 #[derive(Component, Default)]
-pub struct CollectPresent {}
+pub struct CollectPresent {
+    pub station: Option<Entity>, // Only rockets from this station can collect it when set
+}
 
 #[derive(Resource)]
 pub struct SelectedMaterial {
     pub mat: Handle<StandardMaterial>,
+    pub station_mat: Handle<StandardMaterial>, // Presents going to the selected station
 }
 
 pub fn init(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
@@ -35,6 +101,11 @@ pub fn init(mut commands: Commands, mut materials: ResMut<Assets<StandardMateria
             unlit: true,
             ..default()
         }),
+        station_mat: materials.add(StandardMaterial {
+            base_color: Color::rgba_u8(0xf6, 0xd3, 0x2d, 255),
+            unlit: true,
+            ..default()
+        }),
     });
 }
 
@@ -43,141 +114,165 @@ pub fn spawn(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     level_config: Res<LevelConfig>,
+    mut rng: ResMut<GameRng>,
 ) {
-    // Spawn a bunch of junk in 2 layers at different densities.
-    // This would be nice if we drove this from some form of config.
-    // Maybe some kinda level config will come.
-
-    let mesh = meshes.add(Mesh::from(shape::Box::new(
-        level_config.present_size,
-        level_config.present_size,
-        level_config.present_size,
-    )));
-    let mut present_materials = Vec::<Handle<StandardMaterial>>::new();
-    for color in PRESENT_COLORS {
-        let material = materials.add(StandardMaterial {
-            base_color: color,
-            unlit: true,
-            ..default()
-        });
-        present_materials.push(material);
-    }
+    let kinds = PresentKind::ALL
+        .iter()
+        .map(|kind| (*kind, level_config.presents.weight(*kind)))
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect::<Vec<_>>();
+    let kind_index = WeightedIndex::new(kinds.iter().map(|(_, weight)| weight))
+        .expect("level present weights are validated when loading");
+    let golden_material = materials.add(StandardMaterial {
+        base_color: Color::rgb_u8(0xf6, 0xd3, 0x2d),
+        metallic: 1.0,
+        perceptual_roughness: 0.3,
+        ..default()
+    });
 
-    for _ in 0..level_config.low_orbit_presents {
-        let coord = gen_coord(21.0..24.0);
-        let material = present_materials[gen_index(present_materials.len())].clone();
-        spawn_present(
-            &mut commands,
-            mesh.clone(),
-            material,
-            coord,
-            level_config.present_hitbox_size,
+    // Spawn a bunch of junk in layers at different densities as described by the level
+    let mut id = 0;
+    for band in level_config.bands.iter() {
+        let mesh = meshes.add(Mesh::from(shape::Box::new(
+            band.present_size,
+            band.present_size,
+            band.present_size,
+        )));
+        let fragile_mesh = meshes.add(
+            Mesh::try_from(shape::Icosphere {
+                radius: band.present_size * 0.6,
+                subdivisions: 1,
+            })
+            .unwrap(),
         );
-    }
+        let mut present_materials = Vec::<Handle<StandardMaterial>>::new();
+        for color in band.colors.iter() {
+            let material = materials.add(StandardMaterial {
+                base_color: *color,
+                unlit: true,
+                ..default()
+            });
+            present_materials.push(material);
+        }
 
-    for _ in 0..level_config.high_orbit_presents {
-        let coord = gen_coord(24.0..35.0);
-        let material = present_materials[gen_index(present_materials.len())].clone();
-        spawn_present(
-            &mut commands,
-            mesh.clone(),
-            material,
-            coord,
-            level_config.present_hitbox_size,
-        );
+        for _ in 0..band.count {
+            let kind = kinds[kind_index.sample(&mut **rng)].0;
+            let altitude = rng.gen_range(band.altitude.clone());
+            let mut orbit = band.pattern.orbit(&mut **rng, altitude);
+            orbit.angular_velocity *= kind.orbit_speed();
+            let material =
+                present_materials[gen_index(&mut **rng, present_materials.len())].clone();
+            let (mesh, material) = match kind {
+                PresentKind::Fragile => (fragile_mesh.clone(), material),
+                PresentKind::Golden => (mesh.clone(), golden_material.clone()),
+                _ => (mesh.clone(), material),
+            };
+            spawn_present(
+                &mut commands,
+                id,
+                kind,
+                mesh,
+                material,
+                orbit,
+                band.present_hitbox_size,
+            );
+            id += 1;
+        }
     }
 }
 
 fn spawn_present(
     commands: &mut Commands,
+    id: usize,
+    kind: PresentKind,
     mesh: Handle<Mesh>,
     material: Handle<StandardMaterial>,
-    coord: Coord,
+    orbit: Orbit,
     hitbox_size: f32,
 ) {
+    let coord = orbit.to_coord();
+    // Collider scales with the transform so heavy presents are easier to hit too
+    let transform = match kind {
+        PresentKind::Heavy => coord.to_transform().with_scale(Vec3::splat(HEAVY_SCALE)),
+        _ => coord.to_transform(),
+    };
     commands.spawn((
-        Present::default(),
+        Present {
+            id,
+            kind,
+            material: material.clone(),
+        },
+        orbit,
         coord,
         PbrBundle {
             mesh,
             material,
-            transform: coord.to_transform(),
+            transform,
             ..default()
         },
+        Interpolated::new(transform),
         RigidBody::Fixed,
         Collider::cuboid(hitbox_size, hitbox_size, hitbox_size),
         GameElement,
     ));
 }
 
-fn gen_coord<R>(dist_range: R) -> Coord
-where
-    R: SampleRange<f32>,
-{
-    let mut r = rand::thread_rng();
-    Coord {
-        long: r.gen_range(-PI..PI),
-        lat: r.gen_range(-PI..PI),
-        dist: CoordDistance::Orbit(r.gen_range(dist_range)),
-    }
-}
-
-fn gen_index(max: usize) -> usize {
-    let mut r = rand::thread_rng();
+fn gen_index(r: &mut impl Rng, max: usize) -> usize {
     r.gen_range(0..max)
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn cast_ray(
     mut commands: Commands,
-    windows: Query<&Window, With<PrimaryWindow>>,
     rapier_context: Res<RapierContext>,
-    cameras: Query<(&Camera, &GlobalTransform)>,
     action_query: Query<&ActionState<PlayerAction>>,
     present_query: Query<(Entity, &Present)>,
+    station_query: Query<Entity, With<GroundStation>>,
+    mut selected_station: ResMut<SelectedStation>,
     mut next_interaction_state: ResMut<NextState<InteractionState>>,
     interaction_state: Res<State<InteractionState>>,
     selected_material: Res<SelectedMaterial>,
+    mut pointer: ResMut<Pointer>,
 ) {
-    let window = windows.single();
-
-    let Some(cursor_position) = window.cursor_position() else {
-        return;
-    };
-
-    let mut on_entity = false;
-
-    for (camera, camera_transform) in &cameras {
-        // Compute a ray from the mouse position.
-        let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-            return;
-        };
-
-        // Then cast the ray.
-        let hit = rapier_context.cast_ray(
-            ray.origin,
-            ray.direction,
-            f32::MAX,
-            true,
-            QueryFilter::only_fixed(),
-        );
+    // Cast the ray from the mouse position
+    let hit = pointer.ray.and_then(|ray| {
+        rapier_context
+            .cast_ray(
+                ray.origin,
+                ray.direction,
+                f32::MAX,
+                true,
+                QueryFilter::only_fixed(),
+            )
+            .map(|(entity, _toi)| entity)
+    });
+    if pointer.hit != hit {
+        pointer.hit = hit;
+    }
 
-        if let Some((entity, _toi)) = hit {
-            if *interaction_state == InteractionState::OnEntity
-                && action_query.single().just_released(PlayerAction::CanMove)
-                && present_query.contains(entity)
-            {
-                commands.entity(entity).insert(CollectPresent::default());
+    if let Some(entity) = hit {
+        if *interaction_state == InteractionState::OnEntity
+            && action_query.single().just_released(PlayerAction::CanMove)
+        {
+            if present_query.contains(entity) {
+                commands.entity(entity).insert(CollectPresent {
+                    station: selected_station.0,
+                });
 
                 commands
                     .entity(entity)
                     .insert(selected_material.mat.clone());
+            } else if station_query.contains(entity) {
+                selected_station.0 = Some(entity);
             }
-            on_entity = true;
         }
     }
 
-    if on_entity && action_query.single().just_pressed(PlayerAction::CanMove) {
+    // Shift clicks start a selection box instead
+    if hit.is_some()
+        && action_query.single().just_pressed(PlayerAction::CanMove)
+        && !action_query.single().pressed(PlayerAction::Select)
+    {
         next_interaction_state.set(InteractionState::OnEntity);
     }
 
@@ -1,58 +1,518 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
 
-use super::present::{CollectPresent, Present};
+use super::{
+    orbit::Orbit,
+    present::{CollectPresent, Present},
+    routing,
+    score::PresentPickedUp,
+    trajectory, GameData,
+};
 
-#[derive(Component, Default)]
+const LAUNCH_HEIGHT: f32 = 1.5; // How far above the pad the rocket climbs before turning
+const LAUNCH_SPEED: f32 = 3.0; // Speed when going straight up and down from the pad
+const PICKUP_DISTANCE: f32 = 0.1;
+const SWEEP_RADIUS: f32 = 6.0; // Presents this close to a rocket's route get added to it
+const MAX_FUEL: f32 = 80.0;
+const FUEL_PER_DISTANCE: f32 = 1.0;
+const FUEL_PER_ALTITUDE: f32 = 2.0; // Changing altitude costs extra on top of the distance
+const REFUEL_RATE: f32 = 20.0; // Fuel per second while sitting on the pad
+const FUEL_RESERVE: f32 = 1.1; // Margin on estimates as presents move while we fly
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RocketState {
+    #[default]
+    Idle, // Sitting on the pad waiting for a present
+    Launching,
+    Intercepting,
+    Returning,
+    Landing,
+}
+
+#[derive(Component)]
 pub struct Rocket {
-    pub target: Option<Entity>,
+    pub state: RocketState,
+    pub route: VecDeque<Entity>, // Presents still to pick up in the order they will be visited
+    pub cargo: Vec<Entity>,
+    pub capacity: usize,
+    pub speed: f32,
+    pub fuel: f32,
+    pub max_fuel: f32,
+    pub station: Entity,
+    pub home: Transform, // Where the rocket sits on the pad
+}
+
+impl Rocket {
+    pub fn new(station: Entity, home: Transform, speed: f32, capacity: usize) -> Self {
+        Self {
+            state: RocketState::Idle,
+            route: VecDeque::new(),
+            cargo: Vec::new(),
+            capacity,
+            speed,
+            fuel: MAX_FUEL,
+            max_fuel: MAX_FUEL,
+            station,
+            home,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.state == RocketState::Idle
+    }
+
+    pub fn target(&self) -> Option<Entity> {
+        self.route.front().copied()
+    }
+
+    // Can more presents be added to the route before heading home. `load` is the cargo
+    // space already taken by the route and cargo
+    fn has_space(&self, load: usize) -> bool {
+        matches!(
+            self.state,
+            RocketState::Idle | RocketState::Launching | RocketState::Intercepting
+        ) && self.free_space(load) > 0
+    }
+
+    fn free_space(&self, load: usize) -> usize {
+        self.capacity.saturating_sub(load)
+    }
+
+    /// Fuel needed to fly from `from` through `stops` and then land back on the pad
+    pub fn fuel_needed(&self, from: Vec3, stops: &[Vec3]) -> f32 {
+        let hover = self.hover_point();
+        let mut points = vec![from];
+        points.extend_from_slice(stops);
+        points.push(hover);
+
+        let flight = points
+            .windows(2)
+            .map(|w| fuel_cost(&trajectory::plan(w[0], w[1])))
+            .sum::<f32>();
+        let landing = fuel_cost(&[hover, self.home.translation]);
+        (flight + landing) * FUEL_RESERVE
+    }
+
+    /// Could the rocket get to `to` and back with a full tank
+    pub fn can_reach(&self, to: Vec3) -> bool {
+        self.fuel_for_trip(&[to]) <= self.max_fuel
+    }
+
+    // Fuel for a trip round `stops` starting from the pad
+    fn fuel_for_trip(&self, stops: &[Vec3]) -> f32 {
+        let launch = fuel_cost(&[self.home.translation, self.hover_point()]);
+        launch + self.fuel_needed(self.hover_point(), stops)
+    }
+
+    fn up(&self) -> Vec3 {
+        self.home.translation.normalize()
+    }
+
+    // Point above the pad where launches end and landings start
+    fn hover_point(&self) -> Vec3 {
+        self.home.translation + self.up() * LAUNCH_HEIGHT
+    }
 }
 
 #[derive(Component, Default)]
 pub struct RocketTarget {}
 
+/// Present that has been picked up and is on its way to a ground station
+#[derive(Component, Default)]
+pub struct Cargo {}
+
+/// Sent when a present was marked that no rocket has the fuel to reach
+#[derive(Event)]
+pub struct PresentRefused(pub Entity);
+
+/// Sent when a fragile present was picked up by a rocket going too fast
+#[derive(Event)]
+pub struct PresentBroken(pub Entity);
+
+/// Fuel burnt flying along a path. Climbing and diving costs more than flying level
+pub fn fuel_cost(path: &[Vec3]) -> f32 {
+    path.windows(2)
+        .map(|w| {
+            w[0].distance(w[1]) * FUEL_PER_DISTANCE
+                + (w[1].length() - w[0].length()).abs() * FUEL_PER_ALTITUDE
+        })
+        .sum()
+}
+
+#[allow(clippy::type_complexity)]
 pub fn collect_presents(
     mut commands: Commands,
-    mut rocket_query: Query<(Entity, &mut Rocket)>,
-    present_query: Query<(Entity, &CollectPresent, &Present), Without<RocketTarget>>,
+    mut rocket_query: Query<(&mut Rocket, &Transform)>,
+    queued_query: Query<(Entity, &Present, &CollectPresent), Without<RocketTarget>>,
+    orbit_query: Query<&Orbit, With<Present>>,
+    present_query: Query<&Present>,
+    mut refused_events: EventWriter<PresentRefused>,
 ) {
-    for (present, _, _) in present_query.iter() {
-        for (_, mut rocket) in rocket_query.iter_mut() {
-            if rocket.target.is_none() {
-                rocket.target = Some(present);
-                commands.entity(present).insert(RocketTarget::default());
-                break;
+    let position = |entity: &Entity| {
+        orbit_query
+            .get(*entity)
+            .map(|o| o.position())
+            .unwrap_or_default()
+    };
+
+    let cargo_size = |entity: &Entity| {
+        present_query
+            .get(*entity)
+            .map(|p| p.kind.cargo_size())
+            .unwrap_or_default()
+    };
+
+    let mut rockets = rocket_query.iter_mut().collect::<Vec<_>>();
+    let mut loads = rockets
+        .iter()
+        .map(|(rocket, _)| {
+            rocket
+                .route
+                .iter()
+                .chain(&rocket.cargo)
+                .map(cargo_size)
+                .sum()
+        })
+        .collect::<Vec<usize>>();
+
+    // Presents sent to a station can only be collected by that station's rockets
+    let allowed = |rocket: &Rocket, collect: &CollectPresent| {
+        collect
+            .station
+            .is_none_or(|station| rocket.station == station)
+    };
+
+    // Refuse anything no rocket could reach even with a full tank
+    let mut presents = Vec::new();
+    for (entity, present, collect) in queued_query.iter() {
+        let pos = position(&entity);
+        if rockets.iter().any(|(rocket, _)| {
+            allowed(rocket, collect)
+                && rocket.capacity >= present.kind.cargo_size()
+                && rocket.can_reach(pos)
+        }) {
+            presents.push((entity, present, collect));
+        } else {
+            commands
+                .entity(entity)
+                .remove::<CollectPresent>()
+                .insert(present.material.clone());
+            refused_events.send(PresentRefused(entity));
+        }
+    }
+    if presents.is_empty() {
+        return;
+    }
+
+    // Every free cargo spot on every rocket is something a present can be assigned to
+    let slots = rockets
+        .iter()
+        .enumerate()
+        .filter(|(index, (rocket, _))| rocket.has_space(loads[*index]))
+        .flat_map(|(index, (rocket, _))| {
+            std::iter::repeat_n(index, rocket.free_space(loads[index]))
+        })
+        .collect::<Vec<_>>();
+    if slots.is_empty() {
+        return; // Everything is busy. Try again next frame
+    }
+
+    let costs = presents
+        .iter()
+        .map(|(entity, present, collect)| {
+            let pos = position(entity);
+            let rocket_costs = rockets
+                .iter()
+                .enumerate()
+                .map(|(index, (rocket, transform))| {
+                    if !allowed(rocket, collect)
+                        || rocket.free_space(loads[index]) < present.kind.cargo_size()
+                    {
+                        f32::INFINITY
+                    } else if rocket.is_available() {
+                        // Waiting on the pad so cost is the flight from the station. Rockets
+                        // still refueling wait until they have enough
+                        if rocket.fuel_for_trip(&[pos]) <= rocket.fuel {
+                            trajectory::length(&trajectory::plan(rocket.hover_point(), pos))
+                        } else {
+                            f32::INFINITY
+                        }
+                    } else {
+                        // Already flying so only pick up presents close to where it is going
+                        let mut stops = rocket.route.iter().map(position).collect::<Vec<_>>();
+                        let distance =
+                            routing::distance_to_tour(transform.translation, &stops, pos);
+                        stops.push(pos);
+                        if distance < SWEEP_RADIUS
+                            && rocket.fuel_needed(transform.translation, &stops) <= rocket.fuel
+                        {
+                            distance
+                        } else {
+                            f32::INFINITY
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            slots.iter().map(|index| rocket_costs[*index]).collect()
+        })
+        .collect::<Vec<Vec<f32>>>();
+
+    let mut added = vec![Vec::new(); rockets.len()];
+    for ((entity, present, _), slot) in presents.iter().zip(routing::assign(&costs)) {
+        let Some(slot) = slot else {
+            continue;
+        };
+        // Big presents use more than the one slot they were assigned so check they still fit.
+        // If not they are tried again next frame
+        let index = slots[slot];
+        let size = present.kind.cargo_size();
+        if rockets[index].0.free_space(loads[index]) < size {
+            continue;
+        }
+        loads[index] += size;
+        rockets[index].0.route.push_back(*entity);
+        added[index].push(*entity);
+    }
+
+    for (index, added) in added.iter_mut().enumerate() {
+        if added.is_empty() {
+            continue;
+        }
+        let (rocket, transform) = &mut rockets[index];
+
+        // Visit the stops in a sensible order rather than the order they were clicked
+        let stops = rocket.route.iter().map(position).collect::<Vec<_>>();
+        let tour = routing::nearest_neighbour_tour(transform.translation, &stops);
+        let route = tour.iter().map(|i| rocket.route[*i]).collect();
+        rocket.route = route;
+
+        // Each present was affordable on its own but maybe not all of them together. Drop the
+        // new ones from the end of the route until it is, leaving them for the next frame
+        let fuel_needed = |rocket: &Rocket| {
+            let stops = rocket.route.iter().map(position).collect::<Vec<_>>();
+            if rocket.is_available() {
+                rocket.fuel_for_trip(&stops)
+            } else {
+                rocket.fuel_needed(transform.translation, &stops)
             }
+        };
+        while fuel_needed(rocket) > rocket.fuel {
+            let Some(last) = rocket.route.iter().rposition(|e| added.contains(e)) else {
+                break;
+            };
+            let dropped = rocket.route.remove(last);
+            added.retain(|e| Some(*e) != dropped);
+        }
+        for entity in added.iter() {
+            commands.entity(*entity).insert(RocketTarget::default());
+        }
+
+        if rocket.is_available() && !rocket.route.is_empty() {
+            rocket.state = RocketState::Launching;
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_target(
     mut commands: Commands,
     time: Res<Time>,
     mut rocket_query: Query<(Entity, &mut Rocket, &mut Transform), Without<RocketTarget>>,
-    present_query: Query<(Entity, &Present, &Transform), With<RocketTarget>>,
+    present_query: Query<(Entity, &Present, &Orbit)>,
+    cargo_query: Query<&Present, With<Cargo>>,
+    mut game_data: ResMut<GameData>,
+    mut broken_events: EventWriter<PresentBroken>,
+    mut pickup_events: EventWriter<PresentPickedUp>,
 ) {
+    let delta = time.delta_seconds();
+
     for (_, mut rocket, mut rocket_trans) in rocket_query.iter_mut() {
-        if let Some(target) = rocket.target {
-            let (_, _, present_trans) = present_query.get(target).unwrap();
+        let start = rocket_trans.translation;
 
-            let dir = (present_trans.translation - rocket_trans.translation).normalize();
-            let pos = rocket_trans.translation + dir * time.delta_seconds() * 10.0;
+        match rocket.state {
+            RocketState::Idle => {
+                rocket.fuel = (rocket.fuel + REFUEL_RATE * delta).min(rocket.max_fuel);
+            }
+            RocketState::Launching => {
+                let hover = rocket.hover_point();
+                rocket_trans.translation =
+                    move_towards(rocket_trans.translation, hover, delta * LAUNCH_SPEED);
+
+                if rocket_trans.translation == hover {
+                    rocket.state = RocketState::Intercepting;
+                }
+            }
+            RocketState::Intercepting => {
+                let Some(target) = rocket.target() else {
+                    rocket.state = RocketState::Returning;
+                    continue;
+                };
+                let Ok((_, present, orbit)) = present_query.get(target) else {
+                    // Target is gone so move on to the next one
+                    rocket.route.pop_front();
+                    continue;
+                };
 
-            rocket_trans.translation = pos;
-            let t = rocket_trans.looking_at(present_trans.translation, Vec3::Y);
-            rocket_trans.rotation = rocket_trans
-                .rotation
-                .slerp(t.rotation, time.delta_seconds() * 5.0);
+                // Presents drift while we fly so give up on the rest of the route if we would
+                // not make it home. They stay marked so another rocket can pick them up
+                let stops = rocket
+                    .route
+                    .iter()
+                    .filter_map(|e| present_query.get(*e).ok())
+                    .map(|(_, _, o)| o.position())
+                    .collect::<Vec<_>>();
+                if rocket.fuel_needed(rocket_trans.translation, &stops) > rocket.fuel {
+                    for present in rocket.route.drain(..) {
+                        commands.entity(present).remove::<RocketTarget>();
+                    }
+                    rocket.state = RocketState::Returning;
+                    continue;
+                }
 
-            // TODO: Make rockets not go though the earth
+                // Presents keep moving so aim for where it will be when we get there
+                let from = rocket_trans.translation;
+                let aim = orbit
+                    .intercept(|to| trajectory::length(&trajectory::plan(from, to)) / rocket.speed);
+                fly_towards(&mut rocket_trans, aim, rocket.speed, delta);
+
+                // Once we are close to the present pick it up. Anything we would reach next
+                // step counts so fast rockets dont keep flying past it
+                let distance = orbit.position().distance(rocket_trans.translation);
+                if distance < PICKUP_DISTANCE.max(rocket.speed * delta) {
+                    if present.kind.survives_pickup(rocket.speed) {
+                        commands
+                            .entity(target)
+                            .remove::<(Orbit, Collider, CollectPresent, RocketTarget)>()
+                            .insert((Cargo::default(), Visibility::Hidden));
+                        rocket.cargo.push(target);
+                        pickup_events.send(PresentPickedUp(present.kind));
+                    } else {
+                        commands.entity(target).despawn_recursive();
+                        game_data.presents_broken += 1;
+                        broken_events.send(PresentBroken(target));
+                    }
+                    rocket.route.pop_front();
+                    if rocket.route.is_empty() {
+                        rocket.state = RocketState::Returning;
+                    }
+                }
+            }
+            RocketState::Returning => {
+                let hover = rocket.hover_point();
+                fly_towards(&mut rocket_trans, hover, rocket.speed, delta);
 
-            // Once we are close to the present despawn it
-            let distance = present_trans.translation.distance(rocket_trans.translation);
-            if distance < 0.1 {
-                commands.entity(target).despawn_recursive();
-                rocket.target = None;
+                if rocket_trans.translation.distance(hover) < 0.01 {
+                    rocket_trans.translation = hover;
+                    rocket.state = RocketState::Landing;
+                }
+            }
+            RocketState::Landing => {
+                let home = rocket.home;
+                rocket_trans.translation = move_towards(
+                    rocket_trans.translation,
+                    home.translation,
+                    delta * LAUNCH_SPEED,
+                );
+                rocket_trans.rotation = rocket_trans.rotation.slerp(home.rotation, delta * 5.0);
+
+                if rocket_trans.translation == home.translation {
+                    // Presents only count once they are delivered
+                    game_data.coins += cargo_query
+                        .iter_many(&rocket.cargo)
+                        .map(|present| present.kind.value())
+                        .sum::<i32>();
+                    for present in rocket.cargo.drain(..) {
+                        commands.entity(present).despawn_recursive();
+                    }
+                    *rocket_trans = home;
+                    rocket.state = RocketState::Idle;
+                }
             }
         }
+
+        // Always allowed to limp home so dont go below empty
+        let burnt = fuel_cost(&[start, rocket_trans.translation]);
+        rocket.fuel = (rocket.fuel - burnt).max(0.0);
+    }
+}
+
+// Move along a planned path that avoids the earth. Replanned every frame as targets move
+fn fly_towards(transform: &mut Transform, to: Vec3, speed: f32, delta: f32) {
+    let from = transform.translation;
+    let path = trajectory::plan(from, to);
+    // Advancing along the path stops at the end so we dont overshoot on long frames
+    let next = trajectory::advance(&path, delta * speed);
+
+    if next != from {
+        let t = transform.looking_at(next, Vec3::Y);
+        transform.rotation = transform.rotation.slerp(t.rotation, delta * 5.0);
+    }
+    transform.translation = next;
+}
+
+// Straight line move that lands exactly on `to` once it is in reach
+fn move_towards(from: Vec3, to: Vec3, max_distance: f32) -> Vec3 {
+    let offset = to - from;
+    let distance = offset.length();
+    if distance <= max_distance {
+        to
+    } else {
+        from + offset / distance * max_distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::present::PresentKind;
+
+    fn spawn_present(app: &mut App, id: usize, direction: Vec3) -> Entity {
+        let orbit = Orbit::through(25.0, direction, direction.any_orthonormal_vector());
+        app.world
+            .spawn((
+                Present {
+                    id,
+                    kind: PresentKind::Standard,
+                    material: Handle::default(),
+                },
+                orbit,
+                CollectPresent::default(),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn routes_are_cut_to_the_fuel_left() {
+        let mut app = App::new();
+        app.add_event::<PresentRefused>()
+            .add_systems(Update, collect_presents);
+
+        let home = Transform::from_xyz(0.0, 20.0, 0.0);
+        let station = app.world.spawn_empty().id();
+        let near = spawn_present(&mut app, 0, Vec3::new(0.2, 1.0, 0.0).normalize());
+        let far = spawn_present(&mut app, 1, Vec3::new(-0.6, 1.0, 0.3).normalize());
+
+        // Enough fuel for either present on its own but not for both
+        let mut rocket = Rocket::new(station, home, 1.0, 2);
+        let positions = [near, far].map(|e| app.world.get::<Orbit>(e).unwrap().position());
+        rocket.fuel = positions
+            .iter()
+            .map(|pos| rocket.fuel_for_trip(&[*pos]))
+            .fold(0.0, f32::max)
+            + 0.1;
+        assert!(rocket.fuel_for_trip(&positions) > rocket.fuel);
+        let rocket = app.world.spawn((rocket, home)).id();
+
+        app.update();
+
+        let rocket = app.world.get::<Rocket>(rocket).unwrap();
+        assert_eq!(rocket.state, RocketState::Launching);
+        assert_eq!(rocket.route, VecDeque::from([near]));
+        assert!(app.world.get::<RocketTarget>(near).is_some());
+        // The other one is still waiting for a rocket
+        assert!(app.world.get::<RocketTarget>(far).is_none());
+        assert!(app.world.get::<CollectPresent>(far).is_some());
     }
 }
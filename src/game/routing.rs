@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+
+/// Orders `stops` into a tour starting at `start` by always visiting the closest stop next.
+/// Returns indices into `stops`
+pub fn nearest_neighbour_tour(start: Vec3, stops: &[Vec3]) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..stops.len()).collect();
+    let mut tour = Vec::with_capacity(stops.len());
+    let mut current = start;
+
+    while !remaining.is_empty() {
+        let (i, _) = remaining
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                current
+                    .distance_squared(stops[**a])
+                    .total_cmp(&current.distance_squared(stops[**b]))
+            })
+            .unwrap();
+        let next = remaining.swap_remove(i);
+        current = stops[next];
+        tour.push(next);
+    }
+
+    tour
+}
+
+/// Closest distance from `point` to any stop on a tour starting at `start`
+pub fn distance_to_tour(start: Vec3, stops: &[Vec3], point: Vec3) -> f32 {
+    stops
+        .iter()
+        .chain(std::iter::once(&start))
+        .map(|stop| stop.distance(point))
+        .fold(f32::MAX, f32::min)
+}
+
+/// Assigns each row to at most one column so the total cost is as small as possible
+/// (Hungarian algorithm). `costs[row][col]` of `f32::INFINITY` means the pair is not allowed.
+/// Returns the column picked for each row
+pub fn assign(costs: &[Vec<f32>]) -> Vec<Option<usize>> {
+    let rows = costs.len();
+    let cols = costs.first().map_or(0, |c| c.len());
+    if rows == 0 || cols == 0 {
+        return vec![None; rows];
+    }
+
+    // The solver needs at least as many columns as rows so flip the problem if needed
+    if rows > cols {
+        let transposed = (0..cols)
+            .map(|c| (0..rows).map(|r| costs[r][c]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut result = vec![None; rows];
+        for (col, row) in assign(&transposed).into_iter().enumerate() {
+            if let Some(row) = row {
+                result[row] = Some(col);
+            }
+        }
+        return result;
+    }
+
+    // Forbidden pairs get a cost bigger than any real assignment could add up to
+    let forbidden = costs
+        .iter()
+        .flatten()
+        .filter(|c| c.is_finite())
+        .map(|c| c.abs() as f64)
+        .sum::<f64>()
+        * 2.0
+        + 1.0;
+    let cost = |r: usize, c: usize| {
+        let value = costs[r][c];
+        if value.is_finite() {
+            value as f64
+        } else {
+            forbidden
+        }
+    };
+
+    // Potentials and matching are 1 indexed with 0 used as a sentinel
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut matched = vec![0; cols + 1]; // Row matched to each column
+    let mut way = vec![0; cols + 1];
+
+    for row in 1..=rows {
+        matched[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+
+        loop {
+            used[col0] = true;
+            let row0 = matched[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+
+            for col in 1..=cols {
+                if !used[col] {
+                    let reduced = cost(row0 - 1, col - 1) - u[row0] - v[col];
+                    if reduced < min_v[col] {
+                        min_v[col] = reduced;
+                        way[col] = col0;
+                    }
+                    if min_v[col] < delta {
+                        delta = min_v[col];
+                        col1 = col;
+                    }
+                }
+            }
+
+            for col in 0..=cols {
+                if used[col] {
+                    u[matched[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+
+            col0 = col1;
+            if matched[col0] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path
+        loop {
+            let col1 = way[col0];
+            matched[col0] = matched[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut result = vec![None; rows];
+    for col in 1..=cols {
+        let row = matched[col];
+        if row != 0 && costs[row - 1][col - 1].is_finite() {
+            result[row - 1] = Some(col - 1);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assign_beats_greedy() {
+        // Greedy would give row 0 column 0 and leave row 1 with the expensive column
+        let costs = vec![vec![1.0, 2.0], vec![1.0, 10.0]];
+
+        assert_eq!(assign(&costs), vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn assign_more_rows_than_columns() {
+        let costs = vec![vec![5.0], vec![1.0], vec![3.0]];
+
+        assert_eq!(assign(&costs), vec![None, Some(0), None]);
+    }
+
+    #[test]
+    fn assign_more_columns_than_rows() {
+        let costs = vec![vec![4.0, 3.0, 9.0], vec![2.0, 8.0, 1.0]];
+
+        assert_eq!(assign(&costs), vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn assign_skips_forbidden() {
+        let costs = vec![vec![f32::INFINITY, 1.0], vec![f32::INFINITY, 2.0]];
+
+        assert_eq!(assign(&costs), vec![Some(1), None]);
+    }
+
+    #[test]
+    fn assign_empty() {
+        assert_eq!(assign(&[]), Vec::<Option<usize>>::new());
+        assert_eq!(assign(&[vec![], vec![]]), vec![None, None]);
+    }
+
+    #[test]
+    fn tour_visits_closest_first() {
+        let stops = [Vec3::X * 5.0, Vec3::X, Vec3::X * 3.0];
+
+        assert_eq!(nearest_neighbour_tour(Vec3::ZERO, &stops), vec![1, 2, 0]);
+    }
+}
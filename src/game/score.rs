@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{present::PresentKind, GameData, GameTimer, LevelConfig};
+
+const COMBO_WINDOW: f32 = 3.0; // Seconds between pickups to keep a combo going
+const COMBO_STEP: f32 = 0.5; // Extra multiplier for each pickup in a combo
+const MAX_MULTIPLIER: f32 = 4.0;
+const TIME_BONUS: f32 = 10.0; // Points per second left on the clock when the level is won
+
+/// Sent when a rocket picks up a present
+#[derive(Event)]
+pub struct PresentPickedUp(pub PresentKind);
+
+#[derive(Resource)]
+pub struct Score {
+    pub base: i32,        // Points from the presents themselves
+    pub combo_bonus: i32, // Extra points from combo multipliers
+    pub time_bonus: i32,
+    pub combo: u32, // Pickups in the current combo
+    pub best_combo: u32,
+    pub stars: usize, // Only set once the level is over
+    combo_timer: Timer,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            base: 0,
+            combo_bonus: 0,
+            time_bonus: 0,
+            combo: 0,
+            best_combo: 0,
+            stars: 0,
+            combo_timer: Timer::from_seconds(COMBO_WINDOW, TimerMode::Once),
+        }
+    }
+}
+
+impl Score {
+    pub fn total(&self) -> i32 {
+        self.base + self.combo_bonus + self.time_bonus
+    }
+
+    /// Multiplier the next pickup would get if it happened now
+    pub fn multiplier(&self) -> f32 {
+        if self.combo == 0 || self.combo_timer.finished() {
+            1.0
+        } else {
+            (1.0 + self.combo as f32 * COMBO_STEP).min(MAX_MULTIPLIER)
+        }
+    }
+
+    pub fn add_pickup(&mut self, points: i32) {
+        let multiplier = self.multiplier();
+        if multiplier == 1.0 {
+            self.combo = 0;
+        }
+
+        self.base += points;
+        self.combo_bonus += (points as f32 * (multiplier - 1.0)).round() as i32;
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+        self.combo_timer.reset();
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.combo_timer.tick(delta);
+        if self.combo_timer.finished() {
+            self.combo = 0;
+        }
+    }
+
+    /// Number of the thresholds the total score reaches
+    pub fn stars_for(&self, thresholds: [i32; 3]) -> usize {
+        thresholds
+            .iter()
+            .filter(|threshold| self.total() >= **threshold)
+            .count()
+    }
+}
+
+pub fn init(mut commands: Commands) {
+    commands.insert_resource(Score::default());
+}
+
+pub fn update(
+    time: Res<Time>,
+    mut score: ResMut<Score>,
+    mut pickup_events: EventReader<PresentPickedUp>,
+) {
+    score.tick(time.delta());
+
+    for PresentPickedUp(kind) in pickup_events.read() {
+        score.add_pickup(kind.points());
+    }
+}
+
+// Add the time bonus and work out the stars once the level is over
+pub fn finish(
+    mut score: ResMut<Score>,
+    game_data: Res<GameData>,
+    timer: Res<GameTimer>,
+    level_config: Res<LevelConfig>,
+) {
+    if game_data.won {
+        score.time_bonus = (timer.remaining_secs() * TIME_BONUS).round() as i32;
+    }
+    score.stars = score.stars_for(level_config.star_thresholds());
+    info!("Scored {} for {} stars", score.total(), score.stars);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_pickups_build_a_combo() {
+        let mut score = Score::default();
+        score.add_pickup(100);
+        score.tick(Duration::from_secs_f32(1.0));
+        score.add_pickup(100);
+        score.tick(Duration::from_secs_f32(1.0));
+        score.add_pickup(100);
+
+        assert_eq!(score.base, 300);
+        assert_eq!(score.combo_bonus, 50 + 100);
+        assert_eq!(score.best_combo, 3);
+    }
+
+    #[test]
+    fn combo_ends_after_window() {
+        let mut score = Score::default();
+        score.add_pickup(100);
+        score.tick(Duration::from_secs_f32(COMBO_WINDOW + 0.1));
+
+        assert_eq!(score.combo, 0);
+        assert_eq!(score.multiplier(), 1.0);
+
+        score.add_pickup(100);
+        assert_eq!(score.combo_bonus, 0);
+    }
+
+    #[test]
+    fn multiplier_is_capped() {
+        let mut score = Score::default();
+        for _ in 0..20 {
+            score.add_pickup(100);
+        }
+
+        assert_eq!(score.multiplier(), MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn stars_from_thresholds() {
+        let score = Score {
+            base: 900,
+            time_bonus: 200,
+            ..default()
+        };
+
+        assert_eq!(score.stars_for([500, 1000, 2000]), 2);
+        assert_eq!(score.stars_for([5000, 6000, 7000]), 0);
+    }
+}
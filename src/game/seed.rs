@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::LevelConfig;
+
+/// Seed used for every random choice in a game so a layout can be reproduced
+#[derive(Resource, Default)]
+pub struct GameSeed {
+    pub fixed: Option<u64>, // Seed forced from the command line. Takes priority over the level
+    pub current: u64,       // Seed used for the running game
+}
+
+impl GameSeed {
+    pub fn fixed(seed: u64) -> Self {
+        Self {
+            fixed: Some(seed),
+            current: seed,
+        }
+    }
+
+    /// Reads `--seed <n>` from the command line
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args();
+        while let Some(arg) = args.next() {
+            if arg == "--seed" {
+                match args.next().map(|s| s.parse::<u64>()) {
+                    Some(Ok(seed)) => return Some(Self::fixed(seed)),
+                    _ => warn!("--seed expects a number"),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Random number generator seeded from the [`GameSeed`]. Use this instead of `rand::thread_rng`
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(0))
+    }
+}
+
+pub fn init(mut seed: ResMut<GameSeed>, mut rng: ResMut<GameRng>, level_config: Res<LevelConfig>) {
+    seed.current = seed
+        .fixed
+        .or(level_config.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen());
+    rng.0 = StdRng::seed_from_u64(seed.current);
+
+    info!("Using seed {}", seed.current);
+}
@@ -0,0 +1,123 @@
+use bevy::{prelude::*, utils::HashSet};
+use leafwing_input_manager::action_state::ActionState;
+
+use super::{
+    ground_station::{SelectedStation, STATIONS},
+    present::{CollectPresent, Present, SelectedMaterial},
+    rocket::Rocket,
+    GroundStation, PlayerAction,
+};
+
+/// Ring shown around the selected station
+#[derive(Component)]
+pub struct StationMarker;
+
+#[derive(Resource)]
+pub struct MarkerResources {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+pub fn init(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(MarkerResources {
+        mesh: meshes.add(Mesh::from(shape::Torus {
+            radius: 0.7,
+            ring_radius: 0.04,
+            ..default()
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba_u8(0xf6, 0xd3, 0x2d, 255),
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+pub fn cycle_station(
+    action_query: Query<&ActionState<PlayerAction>>,
+    station_query: Query<(Entity, &GroundStation)>,
+    mut selected: ResMut<SelectedStation>,
+) {
+    if !action_query
+        .iter()
+        .any(|action| action.just_pressed(PlayerAction::CycleStation))
+    {
+        return;
+    }
+
+    // Go through the stations in the same order as the station list
+    let mut stations = station_query.iter().collect::<Vec<_>>();
+    stations.sort_by_key(|(_, station)| STATIONS.iter().position(|s| s.name == station.name));
+
+    let current = selected
+        .0
+        .and_then(|entity| stations.iter().position(|(e, _)| *e == entity));
+    // After the last station comes no station, where presents go to whichever rocket is best
+    let next = current.map_or(0, |index| index + 1);
+    selected.0 = stations.get(next).map(|(e, _)| *e);
+}
+
+pub fn update_marker(
+    mut commands: Commands,
+    selected: Res<SelectedStation>,
+    marker_res: Res<MarkerResources>,
+    marker_query: Query<Entity, With<StationMarker>>,
+    station_query: Query<(), With<GroundStation>>,
+) {
+    if !selected.is_changed() {
+        return;
+    }
+
+    for marker in marker_query.iter() {
+        commands.entity(marker).despawn_recursive();
+    }
+
+    if let Some(station) = selected.0.filter(|entity| station_query.contains(*entity)) {
+        let marker = commands
+            .spawn((
+                PbrBundle {
+                    mesh: marker_res.mesh.clone(),
+                    material: marker_res.material.clone(),
+                    // Torus is flat on the XZ plane but the station faces out along Z
+                    transform: Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians())),
+                    ..default()
+                },
+                StationMarker,
+            ))
+            .id();
+        commands.entity(station).add_child(marker);
+    }
+}
+
+// Presents going to the selected station get its colour so it is clear what it is doing
+pub fn highlight_targets(
+    mut commands: Commands,
+    selected: Res<SelectedStation>,
+    selected_material: Res<SelectedMaterial>,
+    rocket_query: Query<&Rocket>,
+    present_query: Query<(Entity, &CollectPresent, &Handle<StandardMaterial>), With<Present>>,
+) {
+    let assigned = rocket_query
+        .iter()
+        .filter(|rocket| selected.0 == Some(rocket.station))
+        .flat_map(|rocket| rocket.route.iter().copied())
+        .collect::<HashSet<_>>();
+
+    for (entity, collect, material) in present_query.iter() {
+        let highlight =
+            selected.0.is_some() && (assigned.contains(&entity) || collect.station == selected.0);
+        let wanted = if highlight {
+            &selected_material.station_mat
+        } else {
+            &selected_material.mat
+        };
+
+        if material != wanted {
+            commands.entity(entity).insert(wanted.clone());
+        }
+    }
+}
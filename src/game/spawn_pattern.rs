@@ -0,0 +1,240 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::Coord;
+
+use super::orbit::Orbit;
+
+/// How the presents of a band are laid out around the earth. Angles are in degrees
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub enum SpawnPattern {
+    /// Evenly over the whole sky
+    #[default]
+    Uniform,
+    /// Bunched around the point where Santa's sack tore, given as latitude and longitude. Most
+    /// presents land within `spread` of it and fly off in every direction from there
+    Cluster { at: (f32, f32), spread: f32 },
+    /// All the way round orbits tilted within `spread` of the one given
+    Ring {
+        inclination: f32,
+        ascending_node: f32,
+        spread: f32,
+    },
+    /// Strung out for `length` along an orbit from `phase`, following each other round
+    Stream {
+        inclination: f32,
+        ascending_node: f32,
+        phase: f32,
+        length: f32,
+        spread: f32,
+    },
+}
+
+impl SpawnPattern {
+    /// Picks the orbit of one present
+    pub fn orbit(&self, r: &mut impl Rng, altitude: f32) -> Orbit {
+        match *self {
+            // Picking the inclination from the cosine keeps the orbit planes evenly spread
+            SpawnPattern::Uniform => Orbit::new(
+                altitude,
+                r.gen_range(-1.0f32..1.0).acos(),
+                r.gen_range(0.0..TAU),
+                r.gen_range(0.0..TAU),
+            ),
+            SpawnPattern::Cluster { at, spread } => {
+                let rip = Coord::from_degrees(Vec2::new(at.0, at.1))
+                    .to_transform()
+                    .translation
+                    .normalize();
+                let direction = scatter(r, rip, spread.to_radians());
+                Orbit::through(altitude, direction, perpendicular(r, direction))
+            }
+            SpawnPattern::Ring {
+                inclination,
+                ascending_node,
+                spread,
+            } => {
+                let ring = Orbit::new(
+                    1.0,
+                    inclination.to_radians(),
+                    ascending_node.to_radians(),
+                    0.0,
+                );
+                let normal = scatter(r, ring.normal(), spread.to_radians());
+                Orbit::with_normal(altitude, normal, r.gen_range(0.0..TAU))
+            }
+            SpawnPattern::Stream {
+                inclination,
+                ascending_node,
+                phase,
+                length,
+                spread,
+            } => {
+                let along = phase + r.gen_range(0.0..=length);
+                let stream = Orbit::new(
+                    1.0,
+                    inclination.to_radians(),
+                    ascending_node.to_radians(),
+                    along.to_radians(),
+                );
+                // Tilting the plane moves the point a little off it so bring it back on
+                let normal = scatter(r, stream.normal(), spread.to_radians());
+                let direction = stream.position().reject_from_normalized(normal).normalize();
+                Orbit::through(altitude, direction, normal)
+            }
+        }
+    }
+
+    /// Spreads and lengths can not be negative
+    pub fn is_valid(&self) -> bool {
+        let valid = |value: f32| value.is_finite() && value >= 0.0;
+        match *self {
+            SpawnPattern::Uniform => true,
+            SpawnPattern::Cluster { spread, .. } | SpawnPattern::Ring { spread, .. } => {
+                valid(spread)
+            }
+            SpawnPattern::Stream { length, spread, .. } => valid(length) && valid(spread),
+        }
+    }
+}
+
+// Normally distributed with a standard deviation of one, using the Box-Muller transform
+fn gaussian(r: &mut impl Rng) -> f32 {
+    let u = 1.0 - r.gen::<f32>(); // Never zero so the log is finite
+    let v = r.gen::<f32>();
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
+
+// Tips a unit vector a normally distributed angle with a standard deviation of `spread` radians
+// in a random direction
+fn scatter(r: &mut impl Rng, direction: Vec3, spread: f32) -> Vec3 {
+    let (a, b) = direction.any_orthonormal_pair();
+    let offset = (a * gaussian(r) + b * gaussian(r)) * spread;
+    let angle = offset.length();
+    if angle == 0.0 {
+        return direction;
+    }
+    Quat::from_axis_angle(direction.cross(offset) / angle, angle) * direction
+}
+
+// Random unit vector at right angles to `direction`
+fn perpendicular(r: &mut impl Rng, direction: Vec3) -> Vec3 {
+    let (a, b) = direction.any_orthonormal_pair();
+    let angle = r.gen_range(0.0..TAU);
+    a * angle.cos() + b * angle.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const SAMPLES: usize = 4000;
+
+    fn directions(pattern: &SpawnPattern) -> Vec<Vec3> {
+        let mut r = StdRng::seed_from_u64(1);
+        (0..SAMPLES)
+            .map(|_| pattern.orbit(&mut r, 25.0).position() / 25.0)
+            .collect()
+    }
+
+    #[test]
+    fn orbit_through_a_point() {
+        let direction = Vec3::new(1.0, 2.0, -0.5).normalize();
+        let normal = direction.any_orthogonal_vector().normalize();
+
+        let orbit = Orbit::through(30.0, direction, normal);
+        assert!(orbit.position().abs_diff_eq(direction * 30.0, 1e-4));
+        assert!(orbit.normal().abs_diff_eq(normal, 1e-5));
+    }
+
+    #[test]
+    fn uniform_covers_the_sphere_evenly() {
+        // Heights on a sphere are evenly spread so half of them are within half the radius
+        // of the equator and there are as many in each hemisphere
+        let directions = directions(&SpawnPattern::Uniform);
+        let near_equator = directions.iter().filter(|d| d.y.abs() < 0.5).count();
+        let mean = directions.iter().sum::<Vec3>() / SAMPLES as f32;
+
+        assert!((near_equator as f32 / SAMPLES as f32 - 0.5).abs() < 0.03);
+        assert!(mean.length() < 0.05);
+    }
+
+    #[test]
+    fn clusters_bunch_around_the_rip() {
+        let pattern = SpawnPattern::Cluster {
+            at: (40.0, -70.0),
+            spread: 5.0,
+        };
+        let rip = Coord::from_degrees(Vec2::new(40.0, -70.0))
+            .to_transform()
+            .translation
+            .normalize();
+
+        // About 86% of a 2D normal distribution is within two standard deviations
+        let close = directions(&pattern)
+            .iter()
+            .filter(|d| d.angle_between(rip) < 10f32.to_radians())
+            .count();
+        assert!((close as f32 / SAMPLES as f32 - 0.86).abs() < 0.03);
+    }
+
+    #[test]
+    fn rings_go_all_the_way_round_one_plane() {
+        let pattern = SpawnPattern::Ring {
+            inclination: 60.0,
+            ascending_node: 30.0,
+            spread: 1.0,
+        };
+        let normal = Orbit::new(1.0, 60f32.to_radians(), 30f32.to_radians(), 0.0).normal();
+
+        let directions = directions(&pattern);
+        assert!(directions.iter().all(|d| d.dot(normal).abs() < 0.1));
+        let mean = directions.iter().sum::<Vec3>() / SAMPLES as f32;
+        assert!(mean.length() < 0.05);
+    }
+
+    #[test]
+    fn streams_follow_their_orbit() {
+        let pattern = SpawnPattern::Stream {
+            inclination: 20.0,
+            ascending_node: 100.0,
+            phase: 45.0,
+            length: 30.0,
+            spread: 0.5,
+        };
+        let start = Orbit::new(
+            1.0,
+            20f32.to_radians(),
+            100f32.to_radians(),
+            45f32.to_radians(),
+        );
+        let middle = start.position_in(15f32.to_radians() / start.angular_velocity);
+
+        for direction in directions(&pattern) {
+            assert!(direction.angle_between(middle) < 18f32.to_radians());
+        }
+    }
+
+    #[test]
+    fn negative_spreads_are_invalid() {
+        assert!(SpawnPattern::Uniform.is_valid());
+        assert!(!SpawnPattern::Cluster {
+            at: (0.0, 0.0),
+            spread: -1.0
+        }
+        .is_valid());
+        assert!(!SpawnPattern::Stream {
+            inclination: 0.0,
+            ascending_node: 0.0,
+            phase: 0.0,
+            length: f32::NAN,
+            spread: 1.0,
+        }
+        .is_valid());
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::coord::GROUND_DISTANCE;
+
+const SAMPLES: usize = 32;
+const CLEARANCE: f32 = 0.5; // Lowest a rocket should fly above the ground
+const ARC_HEIGHT: f32 = 6.0; // Extra height at the top of a flight to the far side of the earth
+
+/// Plans a flight path from `from` to `to` that stays above the ground.
+/// Targets in sight are flown to in a straight line, anything else gets an arc that climbs
+/// early and follows the curve of the earth.
+pub fn plan(from: Vec3, to: Vec3) -> Vec<Vec3> {
+    let from_dist = from.length();
+    let to_dist = to.length();
+    // Never ask the path to go lower than where it starts or ends
+    let floor = (GROUND_DISTANCE + CLEARANCE).min(from_dist).min(to_dist);
+
+    if segment_min_distance(from, to) >= floor {
+        return vec![from, to];
+    }
+
+    let from_dir = from / from_dist;
+    let to_dir = to / to_dist;
+    let rotation = Quat::from_rotation_arc(from_dir, to_dir);
+    let angle = from_dir.angle_between(to_dir);
+    let lift = ARC_HEIGHT * angle / std::f32::consts::PI;
+
+    (0..=SAMPLES)
+        .map(|i| {
+            let u = i as f32 / SAMPLES as f32;
+            let dir = Quat::IDENTITY.slerp(rotation, u) * from_dir;
+            // Ease out so the rocket gains most of its height at the start
+            let climb = 1.0 - (1.0 - u) * (1.0 - u);
+            let dist =
+                from_dist + (to_dist - from_dist) * climb + lift * (u * std::f32::consts::PI).sin();
+            dir * dist.max(floor)
+        })
+        .collect()
+}
+
+pub fn length(path: &[Vec3]) -> f32 {
+    path.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+/// Point `distance` along the path. Stops at the end of the path
+pub fn advance(path: &[Vec3], distance: f32) -> Vec3 {
+    let mut remaining = distance;
+    for w in path.windows(2) {
+        let segment = w[0].distance(w[1]);
+        if remaining <= segment && segment > 0.0 {
+            return w[0].lerp(w[1], remaining / segment);
+        }
+        remaining -= segment;
+    }
+    path.last().copied().unwrap_or_default()
+}
+
+/// Closest the straight line between two points gets to the center of the earth
+pub fn segment_min_distance(from: Vec3, to: Vec3) -> f32 {
+    let dir = to - from;
+    let len_sq = dir.length_squared();
+    if len_sq <= f32::EPSILON {
+        return from.length();
+    }
+    let t = (-from.dot(dir) / len_sq).clamp(0.0, 1.0);
+    (from + dir * t).length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_above_ground(path: &[Vec3]) {
+        for w in path.windows(2) {
+            for i in 0..=10 {
+                let point = w[0].lerp(w[1], i as f32 / 10.0);
+                assert!(
+                    point.length() > GROUND_DISTANCE,
+                    "{:?} is inside the earth",
+                    point
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn straight_to_visible_target() {
+        let from = Vec3::new(0.0, 0.0, 20.5);
+        let to = Vec3::new(0.0, 1.0, 24.0);
+        let path = plan(from, to);
+
+        assert_eq!(path, vec![from, to]);
+        assert_above_ground(&path);
+    }
+
+    #[test]
+    fn around_to_far_side() {
+        let from = Vec3::new(0.0, 0.0, 20.5);
+        let to = Vec3::new(0.0, 0.0, -22.0);
+        let path = plan(from, to);
+
+        assert!(path.len() > 2);
+        assert_eq!(*path.first().unwrap(), from);
+        assert!(path.last().unwrap().distance(to) < 0.001);
+        assert_above_ground(&path);
+    }
+
+    #[test]
+    fn many_targets_stay_above_ground() {
+        let from = Vec3::new(20.5, 0.0, 0.0);
+        for i in 0..64 {
+            let long = i as f32 * 0.7;
+            let lat = (i as f32 * 0.37).sin() * 1.5;
+            let dist = 21.0 + (i % 14) as f32;
+            let to = Quat::from_rotation_y(long) * Quat::from_rotation_x(-lat) * Vec3::Z * dist;
+            assert_above_ground(&plan(from, to));
+        }
+    }
+
+    #[test]
+    fn advance_along_path() {
+        let path = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)];
+
+        assert_eq!(length(&path), 2.0);
+        assert_eq!(advance(&path, 0.5), Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(advance(&path, 1.5), Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(advance(&path, 5.0), Vec3::new(1.0, 1.0, 0.0));
+    }
+}
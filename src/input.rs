@@ -1,21 +1,230 @@
-use bevy::prelude::*;
-use leafwing_input_manager::prelude::*;
+use bevy::{input::mouse::MouseMotion, prelude::*, window::PrimaryWindow};
+use leafwing_input_manager::{
+    axislike::{AxisType, VirtualAxis},
+    plugin::InputManagerSystem,
+    prelude::*,
+    user_input::InputKind,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::{game::Player, game::PlayerAction, state::AppState};
+use crate::{game::Player, game::PlayerAction, replay::Playback, state::AppState};
 
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_plugins(InputManagerPlugin::<PlayerAction>::default())
+            .init_resource::<Bindings>()
+            .init_resource::<Reticle>()
+            .init_resource::<Pointer>()
             .add_systems(OnEnter(AppState::InGame), add_player_input)
-            .add_systems(OnExit(AppState::InGame), remove_player_input);
+            .add_systems(OnExit(AppState::InGame), remove_player_input)
+            .add_systems(Update, (update_player_input, update_reticle))
+            .add_systems(
+                PreUpdate,
+                update_pointer
+                    .after(InputManagerSystem::Update)
+                    .run_if(not(resource_exists::<Playback>())),
+            );
+    }
+}
+
+/// Inputs for every action. Starts as the defaults and can be changed on the controls screen
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bindings(pub InputMap<PlayerAction>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self(create_map())
+    }
+}
+
+impl Bindings {
+    /// Actions missing from saved bindings, such as ones added in a newer version, get their
+    /// default inputs
+    pub fn with_defaults(mut self) -> Self {
+        let defaults = create_map();
+        for action in PlayerAction::variants() {
+            if self.0.get(action).is_none_or(|inputs| inputs.is_empty()) {
+                if let Some(inputs) = defaults.get(action) {
+                    self.0
+                        .insert_multiple(inputs.iter().map(|i| (i.clone(), action)));
+                }
+            }
+        }
+        self
+    }
+
+    /// Another action already using `input`, including as one of the keys of a virtual axis
+    pub fn conflict(&self, action: PlayerAction, input: &UserInput) -> Option<PlayerAction> {
+        let kinds = input_kinds(input);
+        self.0
+            .iter()
+            .find(|(other, inputs)| {
+                **other != action
+                    && inputs
+                        .iter()
+                        .any(|i| input_kinds(i).iter().any(|kind| kinds.contains(kind)))
+            })
+            .map(|(other, _)| *other)
+    }
+
+    /// Replaces the inputs for `action` of the same class as `input`. Rebinding a key keeps
+    /// the gamepad button and the other way around, and rebinding an axis to the mouse keeps
+    /// the keys that stand in for it
+    pub fn rebind(&mut self, action: PlayerAction, input: UserInput) {
+        let class = InputClass::of(&input);
+        let kept = self
+            .0
+            .get(action)
+            .map(|inputs| {
+                inputs
+                    .iter()
+                    .filter(|i| InputClass::of(i) != class)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        self.0.clear_action(action);
+        self.0
+            .insert_multiple(kept.into_iter().map(|i| (i, action)));
+        self.0.insert(input, action);
+    }
+}
+
+// Inputs that replace each other when rebinding
+#[derive(PartialEq, Eq)]
+enum InputClass {
+    Gamepad,
+    VirtualKeys, // Keys pretending to be an axis, like WASD
+    KeyboardAndMouse,
+}
+
+impl InputClass {
+    fn of(input: &UserInput) -> Self {
+        if is_gamepad(input) {
+            InputClass::Gamepad
+        } else if matches!(input, UserInput::VirtualDPad(_) | UserInput::VirtualAxis(_)) {
+            InputClass::VirtualKeys
+        } else {
+            InputClass::KeyboardAndMouse
+        }
+    }
+}
+
+fn input_kinds(input: &UserInput) -> Vec<InputKind> {
+    match input {
+        UserInput::Single(kind) => vec![*kind],
+        UserInput::Chord(kinds) => kinds.clone(),
+        UserInput::VirtualDPad(dpad) => vec![dpad.up, dpad.down, dpad.left, dpad.right],
+        UserInput::VirtualAxis(axis) => vec![axis.negative, axis.positive],
+    }
+}
+
+pub fn is_gamepad(input: &UserInput) -> bool {
+    let is_gamepad_kind = |kind: &InputKind| match kind {
+        InputKind::GamepadButton(_) => true,
+        InputKind::SingleAxis(axis) => matches!(axis.axis_type, AxisType::Gamepad(_)),
+        InputKind::DualAxis(axis) => matches!(axis.x.axis_type, AxisType::Gamepad(_)),
+        _ => false,
+    };
+
+    match input {
+        UserInput::Single(kind) => is_gamepad_kind(kind),
+        UserInput::Chord(kinds) => kinds.iter().any(is_gamepad_kind),
+        UserInput::VirtualDPad(dpad) => is_gamepad_kind(&dpad.up),
+        UserInput::VirtualAxis(axis) => is_gamepad_kind(&axis.positive),
+    }
+}
+
+/// Stands in for the mouse cursor when playing with a gamepad
+#[derive(Resource, Default)]
+pub struct Reticle {
+    pub active: bool,
+    pub position: Vec2,
+}
+
+const RETICLE_SPEED: f32 = 600.0; // Pixels per second at full stick
+
+/// What the player is pointing at this frame. Filled in from the cursor or reticle, or from a
+/// replay while one is playing
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct Pointer {
+    pub position: Option<Vec2>, // Screen position
+    pub ray: Option<Ray>,       // From the camera through `position`
+    pub hit: Option<Entity>,    // Whatever the ray hit, set by `present::cast_ray`
+}
+
+/// Where the player is pointing. The reticle while a gamepad is in use, otherwise the mouse
+fn cursor_position(window: &Window, reticle: &Reticle) -> Option<Vec2> {
+    if reticle.active {
+        Some(reticle.position)
+    } else {
+        window.cursor_position()
+    }
+}
+
+fn update_pointer(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    reticle: Res<Reticle>,
+    mut pointer: ResMut<Pointer>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let position = cursor_position(window, &reticle);
+    let ray = position.and_then(|position| {
+        let (camera, camera_transform) = cameras.iter().next()?;
+        camera.viewport_to_world(camera_transform, position)
+    });
+
+    if pointer.position != position || pointer.ray != ray {
+        pointer.position = position;
+        pointer.ray = ray;
+    }
+}
+
+fn update_reticle(
+    time: Res<Time>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    action_query: Query<&ActionState<PlayerAction>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut reticle: ResMut<Reticle>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+
+    // Use whatever was touched last
+    if mouse_motion.read().count() > 0 {
+        reticle.active = false;
+    }
+    let aim = action_query
+        .iter()
+        .filter_map(|action| action.axis_pair(PlayerAction::Aim))
+        .map(|axis| axis.xy())
+        .next()
+        .unwrap_or_default();
+    if !reticle.active && (aim != Vec2::ZERO || gamepad_buttons.get_just_pressed().len() > 0) {
+        reticle.active = true;
+        reticle.position = size / 2.0;
+    }
+
+    if reticle.active {
+        // Screen y goes down but stick y goes up
+        let delta = Vec2::new(aim.x, -aim.y) * RETICLE_SPEED * time.delta_seconds();
+        reticle.position = (reticle.position + delta).clamp(Vec2::ZERO, size);
     }
 }
 
 pub fn add_player_input(
     mut commands: Commands,
     query: Query<Entity, (With<Player>, Without<ActionState<PlayerAction>>)>,
+    bindings: Res<Bindings>,
 ) {
     for player in query.iter() {
         info!("Adding input to player {:#?}", player);
@@ -24,7 +233,7 @@ pub fn add_player_input(
             .entity(player)
             .insert(InputManagerBundle::<PlayerAction> {
                 action_state: ActionState::default(),
-                input_map: create_map(),
+                input_map: bindings.0.clone(),
             });
     }
 }
@@ -43,11 +252,203 @@ pub fn remove_player_input(
     }
 }
 
+// Bindings can be changed from the pause menu so swap them in while playing
+fn update_player_input(bindings: Res<Bindings>, mut query: Query<&mut InputMap<PlayerAction>>) {
+    if !bindings.is_changed() {
+        return;
+    }
+
+    for mut input_map in query.iter_mut() {
+        *input_map = bindings.0.clone();
+    }
+}
+
+// By key rather than by position like `VirtualDPad::wasd` so it can be checked for conflicts
+fn wasd() -> VirtualDPad {
+    VirtualDPad {
+        up: KeyCode::W.into(),
+        down: KeyCode::S.into(),
+        left: KeyCode::A.into(),
+        right: KeyCode::D.into(),
+    }
+}
+
 pub fn create_map() -> InputMap<PlayerAction> {
     InputMap::default()
         .insert(MouseButton::Left, PlayerAction::CanMove)
         .insert(DualAxis::mouse_motion(), PlayerAction::Move)
         .insert(SingleAxis::mouse_wheel_y(), PlayerAction::Zoom)
         .insert(KeyCode::Escape, PlayerAction::Pause)
+        .insert(KeyCode::Tab, PlayerAction::CycleStation)
+        .insert(KeyCode::ShiftLeft, PlayerAction::Select)
+        .insert(KeyCode::ShiftRight, PlayerAction::Select)
+        .insert(GamepadButtonType::South, PlayerAction::CanMove)
+        .insert(GamepadButtonType::Start, PlayerAction::Pause)
+        .insert(GamepadButtonType::RightTrigger, PlayerAction::CycleStation)
+        .insert(GamepadButtonType::West, PlayerAction::Select)
+        .insert(DualAxis::left_stick(), PlayerAction::OrbitAxis)
+        .insert(wasd(), PlayerAction::OrbitAxis)
+        .insert(VirtualDPad::arrow_keys(), PlayerAction::OrbitAxis)
+        .insert(
+            VirtualAxis {
+                negative: GamepadButtonType::LeftTrigger2.into(),
+                positive: GamepadButtonType::RightTrigger2.into(),
+            },
+            PlayerAction::ZoomAxis,
+        )
+        .insert(
+            VirtualAxis::from_keys(KeyCode::Minus, KeyCode::Equals),
+            PlayerAction::ZoomAxis,
+        )
+        .insert(
+            VirtualAxis::from_keys(KeyCode::NumpadSubtract, KeyCode::NumpadAdd),
+            PlayerAction::ZoomAxis,
+        )
+        .insert(KeyCode::R, PlayerAction::ResetCamera)
+        .insert(KeyCode::F, PlayerAction::Focus)
+        .insert(GamepadButtonType::North, PlayerAction::Focus)
+        .insert(GamepadButtonType::RightThumb, PlayerAction::ResetCamera)
+        .insert(DualAxis::right_stick(), PlayerAction::Aim)
         .build()
 }
+
+/// Short name for an input to show on the controls screen
+pub fn input_label(input: &UserInput) -> String {
+    match input {
+        UserInput::Single(InputKind::Keyboard(key)) => format!("{:?}", key),
+        UserInput::Single(InputKind::Mouse(button)) => format!("mouse {:?}", button),
+        UserInput::Single(InputKind::DualAxis(axis)) if *axis == DualAxis::mouse_motion() => {
+            "mouse".into()
+        }
+        UserInput::Single(InputKind::DualAxis(axis)) if *axis == DualAxis::mouse_wheel() => {
+            "mouse wheel".into()
+        }
+        UserInput::Single(InputKind::SingleAxis(axis)) if *axis == SingleAxis::mouse_wheel_y() => {
+            "mouse wheel".into()
+        }
+        UserInput::Single(InputKind::SingleAxis(axis)) if *axis == SingleAxis::mouse_motion_y() => {
+            "mouse up and down".into()
+        }
+        UserInput::Single(InputKind::GamepadButton(button)) => format!("pad {:?}", button),
+        UserInput::Single(InputKind::DualAxis(axis)) if *axis == DualAxis::left_stick() => {
+            "left stick".into()
+        }
+        UserInput::Single(InputKind::DualAxis(axis)) if *axis == DualAxis::right_stick() => {
+            "right stick".into()
+        }
+        UserInput::VirtualDPad(dpad) if *dpad == wasd() => "WASD".into(),
+        UserInput::VirtualDPad(dpad) if *dpad == VirtualDPad::arrow_keys() => "arrow keys".into(),
+        UserInput::VirtualAxis(axis)
+            if *axis == VirtualAxis::from_keys(KeyCode::Minus, KeyCode::Equals) =>
+        {
+            "- and =".into()
+        }
+        UserInput::VirtualAxis(axis)
+            if *axis == VirtualAxis::from_keys(KeyCode::NumpadSubtract, KeyCode::NumpadAdd) =>
+        {
+            "numpad - and +".into()
+        }
+        UserInput::VirtualAxis(VirtualAxis {
+            negative: InputKind::GamepadButton(GamepadButtonType::LeftTrigger2),
+            positive: InputKind::GamepadButton(GamepadButtonType::RightTrigger2),
+        }) => "triggers".into(),
+        input => input.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicts_with_other_actions() {
+        let bindings = Bindings::default();
+        let tab = UserInput::from(KeyCode::Tab);
+
+        assert_eq!(
+            bindings.conflict(PlayerAction::Pause, &tab),
+            Some(PlayerAction::CycleStation)
+        );
+        assert_eq!(bindings.conflict(PlayerAction::CycleStation, &tab), None);
+        assert_eq!(
+            bindings.conflict(PlayerAction::Pause, &KeyCode::P.into()),
+            None
+        );
+    }
+
+    #[test]
+    fn keys_of_virtual_inputs_conflict() {
+        let bindings = Bindings::default();
+
+        assert_eq!(
+            bindings.conflict(PlayerAction::Pause, &KeyCode::W.into()),
+            Some(PlayerAction::OrbitAxis)
+        );
+        assert_eq!(
+            bindings.conflict(PlayerAction::ResetCamera, &KeyCode::Equals.into()),
+            Some(PlayerAction::ZoomAxis)
+        );
+    }
+
+    #[test]
+    fn rebind_replaces_inputs() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(PlayerAction::Select, KeyCode::ControlLeft.into());
+
+        // Both shift keys are replaced but the gamepad binding is kept
+        assert_eq!(
+            bindings.0.get(PlayerAction::Select),
+            Some(&vec![
+                UserInput::from(GamepadButtonType::West),
+                UserInput::from(KeyCode::ControlLeft)
+            ])
+        );
+
+        bindings.rebind(PlayerAction::Select, GamepadButtonType::North.into());
+        assert_eq!(
+            bindings.0.get(PlayerAction::Select),
+            Some(&vec![
+                UserInput::from(KeyCode::ControlLeft),
+                UserInput::from(GamepadButtonType::North)
+            ])
+        );
+    }
+
+    #[test]
+    fn rebinding_an_axis_to_the_mouse_keeps_its_keys() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(PlayerAction::OrbitAxis, DualAxis::mouse_motion().into());
+        bindings.rebind(PlayerAction::ZoomAxis, SingleAxis::mouse_wheel_y().into());
+
+        let orbit = bindings.0.get(PlayerAction::OrbitAxis).unwrap();
+        assert!(orbit.contains(&wasd().into()));
+        assert!(orbit.contains(&VirtualDPad::arrow_keys().into()));
+        assert!(orbit.contains(&DualAxis::left_stick().into()));
+        assert!(orbit.contains(&DualAxis::mouse_motion().into()));
+        let zoom = bindings.0.get(PlayerAction::ZoomAxis).unwrap();
+        assert!(zoom.contains(&VirtualAxis::from_keys(KeyCode::Minus, KeyCode::Equals).into()));
+        assert!(zoom.contains(&SingleAxis::mouse_wheel_y().into()));
+
+        // Binding it to the mouse again replaces the last mouse input
+        bindings.rebind(PlayerAction::OrbitAxis, DualAxis::mouse_wheel().into());
+        let orbit = bindings.0.get(PlayerAction::OrbitAxis).unwrap();
+        assert!(!orbit.contains(&DualAxis::mouse_motion().into()));
+        assert_eq!(orbit.len(), 4);
+    }
+
+    #[test]
+    fn missing_actions_get_defaults() {
+        let mut map = InputMap::default();
+        map.insert(KeyCode::P, PlayerAction::Pause);
+        let bindings = Bindings(map).with_defaults();
+
+        assert_eq!(
+            bindings.0.get(PlayerAction::Pause),
+            Some(&vec![UserInput::from(KeyCode::P)])
+        );
+        assert_eq!(
+            bindings.0.get(PlayerAction::CanMove),
+            create_map().get(PlayerAction::CanMove)
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::camera::CameraPlugin;
-use crate::game::GamePlugin;
+use crate::game::{auto_player::AutoPlayerPlugin, GamePlugin, InteractionPlugin};
 use crate::scene::ScenePlugin;
 use crate::state::AppState;
 use asset::LoadingAssets;
@@ -16,7 +16,7 @@ use bevy_editor_pls::EditorPlugin;
 
 pub mod camera;
 mod coord;
-use camera::orbit::OrbitCamera;
+use camera::orbit::{OrbitCamera, DEFAULT_DISTANCE};
 pub use coord::Coord;
 use game::{Player, PlayerAction};
 use input::InputPlugin;
@@ -25,7 +25,11 @@ use state::{GameState, InteractionState};
 pub mod asset;
 pub mod game;
 pub mod input;
+pub mod replay;
+pub mod save;
 pub mod scene;
+pub mod settings;
+pub mod simulation;
 pub mod state;
 pub mod ui;
 
@@ -45,7 +49,6 @@ impl Plugin for ApplicationPlugin {
                 color: Color::WHITE,
                 brightness: 0.05,
             })
-            .insert_resource(Msaa::Sample4)
             .add_plugins((DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
                     title: "Santa F**ked Up".into(),
@@ -56,11 +59,9 @@ impl Plugin for ApplicationPlugin {
                 }),
                 ..default()
             }),))
-            .add_state::<AppState>()
-            .add_state::<GameState>()
-            .add_state::<InteractionState>()
-            .add_plugins((ScenePlugin, GamePlugin, CameraPlugin, InputPlugin))
-            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_plugins(SimulationPlugin)
+            .add_plugins((ScenePlugin, InteractionPlugin, CameraPlugin, InputPlugin))
+            .add_plugins((settings::SettingsPlugin, save::SavePlugin))
             .add_plugins(bevy_framepace::FramepacePlugin)
             .add_systems(Startup, setup_camera)
             .add_plugins((
@@ -71,11 +72,18 @@ impl Plugin for ApplicationPlugin {
                 ui::loading::LoadingPlugin,
                 ui::game::GamePlugin,
                 ui::post::PostGamePlugin,
+                ui::station::StationPanelPlugin,
+                ui::settings::SettingsMenuPlugin,
+                ui::controls::ControlsMenuPlugin,
+                ui::navigation::NavigationPlugin,
+                ui::attract::AttractPlugin,
                 //ui::diagnostics::DiagnosticsPlugin,
             ))
-            .insert_resource(LoadingAssets::default())
             .add_systems(Update, (handle_pause, cubemap_loaded));
 
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(PostUpdate, replay::write);
+
         //#[cfg(debug_assertions)]
         #[cfg(feature = "editor")]
         app.add_plugins(RapierDebugRenderPlugin::default());
@@ -85,6 +93,21 @@ impl Plugin for ApplicationPlugin {
     }
 }
 
+/// The game rules without anything to show them or play them with. Runs on top of either
+/// `DefaultPlugins` or `simulation::HeadlessPlugin`
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_state::<GameState>()
+            .add_state::<InteractionState>()
+            .add_plugins((GamePlugin, AutoPlayerPlugin, replay::ReplayPlugin))
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .insert_resource(LoadingAssets::default());
+    }
+}
+
 pub fn setup_camera(mut commands: Commands, asset_server: Res<AssetServer>) {
     let transform = Transform::from_translation(Vec3::new(0.0, 1.0, 5.0))
         * Transform::from_rotation(Quat::from_rotation_y(0.0_f32.to_radians()));
@@ -102,7 +125,7 @@ pub fn setup_camera(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         OrbitCamera::new(),
-        Coord::from_dist(80.0),
+        Coord::from_dist(DEFAULT_DISTANCE),
         Player::default(),
         Skybox(skybox.clone()),
     ));
@@ -1,6 +1,27 @@
 use bevy::prelude::*;
-use sfu::ApplicationPlugin;
+use sfu::{
+    game::GameSeed,
+    replay::{Playback, Replay},
+    ApplicationPlugin,
+};
 
 fn main() {
-    App::new().add_plugins(ApplicationPlugin).run();
+    let mut app = App::new();
+    app.add_plugins(ApplicationPlugin);
+
+    if let Some(seed) = GameSeed::from_args() {
+        app.insert_resource(seed);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(replay) = Replay::from_args() {
+        match Playback::new(replay) {
+            Ok(playback) => {
+                app.insert_resource(playback);
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    app.run();
 }
@@ -0,0 +1,729 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::utils::thiserror;
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use leafwing_input_manager::{
+    action_state::ActionState, axislike::DualAxisData, input_map::InputMap,
+    plugin::InputManagerSystem, Actionlike,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::asset::LoadError;
+use crate::game::{
+    campaign::{Campaign, LEVELS},
+    ground_station::{self, GroundStation, Upgrade, UpgradeStation},
+    present::{CollectPresent, Present},
+    seed, GameSeed, Player, PlayerAction,
+};
+use crate::input::{Bindings, Pointer};
+use crate::state::{AppState, GameState};
+
+/// Version written to new replays. Replays from any other version are refused since they would
+/// not play back the same
+const VERSION: u32 = 5;
+
+#[cfg(not(target_arch = "wasm32"))]
+const KEEP: usize = 10; // Older replay files are deleted
+
+// Records every game so it can be played back exactly, and plays back a `Playback` if there is one
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (
+                start_recording
+                    .after(seed::init)
+                    .run_if(not(resource_exists::<Playback>())),
+                start_playback
+                    .after(crate::input::add_player_input)
+                    .run_if(resource_exists::<Playback>()),
+            ),
+        )
+        .add_systems(
+            PreUpdate,
+            play_input
+                .after(InputManagerSystem::Update)
+                .run_if(resource_exists::<Playback>())
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            Update,
+            play_commands
+                .before(ground_station::apply_upgrades)
+                .run_if(resource_exists::<Playback>())
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                record.run_if(resource_exists::<Recording>()),
+                (play_state, check).run_if(resource_exists::<Playback>()),
+            )
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Last, advance.run_if(resource_exists::<Playback>()))
+        .add_systems(OnExit(AppState::InGame), (finish_recording, end_playback))
+        .add_systems(OnExit(AppState::PostGame), forget_played_back)
+        .add_systems(OnEnter(AppState::StartMenu), forget_played_back)
+        // The level never started so there is nothing to play back
+        .add_systems(
+            OnExit(AppState::Loading),
+            end_playback.run_if(resource_exists::<LoadError>()),
+        );
+    }
+}
+
+/// A recorded game. Everything that came from the player is kept for each frame along with how
+/// long the frame took, which together with the seed is enough to play the game again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub level: String, // Path of the level
+    pub seed: u64,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub delta: Duration,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayEvent {
+    /// An action changed. Holds its whole new state
+    Action {
+        action: PlayerAction,
+        pressed: bool,
+        value: f32,
+        axis_pair: Option<Vec2>,
+    },
+    /// The pointer moved or now points at something else. The hit is only used to check the
+    /// playback has not drifted from the recording
+    Pointer {
+        position: Option<Vec2>,
+        ray: Option<(Vec3, Vec3)>, // Origin and direction
+        hit: Option<Target>,
+    },
+    /// A present was marked for collection, however that happened
+    Collect {
+        present: usize,
+        station: Option<String>,
+    },
+    Upgrade {
+        station: String,
+        upgrade: Upgrade,
+    },
+    /// Paused or unpaused, including from the pause menu
+    GameState(GameState),
+}
+
+/// Something in the level, named in a way that stays the same between runs of a seed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    Present(usize),
+    Station(String),
+    Other,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Could not read replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse replay: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write replay: {0}")]
+    Format(#[from] ron::Error),
+    #[error("Replay is from another version ({0})")]
+    Version(u32),
+    #[error("Replay is of an unknown level ({0})")]
+    UnknownLevel(String),
+}
+
+// Read first to find out whether the rest can be read
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+impl Replay {
+    pub fn new(level: &str, seed: u64) -> Self {
+        Self {
+            version: VERSION,
+            level: level.into(),
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let header = ron::from_str::<ReplayHeader>(text)?;
+        if header.version != VERSION {
+            return Err(ReplayError::Version(header.version));
+        }
+        Ok(ron::from_str::<Replay>(text)?)
+    }
+
+    // Not pretty printed since there is an entry for every frame
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        Ok(ron::to_string(self)?)
+    }
+
+    /// Index of the level in the campaign
+    pub fn level_index(&self) -> Result<usize, ReplayError> {
+        LEVELS
+            .iter()
+            .position(|level| level.path == self.level)
+            .ok_or_else(|| ReplayError::UnknownLevel(self.level.clone()))
+    }
+
+    /// Reads the file given by `--replay <path>` on the command line
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args();
+        while let Some(arg) = args.next() {
+            if arg == "--replay" {
+                let Some(path) = args.next() else {
+                    warn!("--replay expects a file");
+                    return None;
+                };
+                let result = std::fs::read_to_string(&path)
+                    .map_err(ReplayError::from)
+                    .and_then(|text| Self::parse(&text));
+                match result {
+                    Ok(replay) => return Some(replay),
+                    Err(e) => error!("{}: {}", path, e),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The game being recorded
+#[derive(Resource)]
+pub struct Recording {
+    replay: Replay,
+    actions: HashMap<PlayerAction, ReplayEvent>, // Last recorded state of each action
+    pointer: Option<ReplayEvent>,
+}
+
+/// The most recently finished recording
+#[derive(Resource)]
+pub struct LastReplay(pub Replay);
+
+/// The game on now was started from a replay, so it is not the player's and does not count
+/// towards the campaign. Kept until the game is over even if the replay ends before that
+#[derive(Resource)]
+pub struct PlayedBack;
+
+/// Plays a replay in place of the player. Insert it before the replay's level is loaded
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    level: usize,
+    frame: usize,
+    hit: Option<Target>, // Where the recording says the pointer is pointing
+    strategy: Option<TimeUpdateStrategy>, // What time was doing before playback took it over
+    diverged: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Result<Self, ReplayError> {
+        Ok(Self {
+            level: replay.level_index()?,
+            replay,
+            frame: 0,
+            hit: None,
+            strategy: None,
+            diverged: false,
+        })
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    fn events(&self) -> &[ReplayEvent] {
+        self.replay
+            .frames
+            .get(self.frame)
+            .map_or(&[], |frame| &frame.events)
+    }
+}
+
+fn action_event(action: PlayerAction, action_state: &ActionState<PlayerAction>) -> ReplayEvent {
+    let data = action_state.action_data(action);
+    ReplayEvent::Action {
+        action,
+        pressed: data.state.pressed(),
+        value: data.value,
+        axis_pair: data.axis_pair.map(|axis| axis.xy()),
+    }
+}
+
+fn target(
+    entity: Entity,
+    present_query: &Query<&Present>,
+    station_query: &Query<(Entity, &GroundStation)>,
+) -> Target {
+    if let Ok(present) = present_query.get(entity) {
+        Target::Present(present.id)
+    } else if let Ok((_, station)) = station_query.get(entity) {
+        Target::Station(station.name.into())
+    } else {
+        Target::Other
+    }
+}
+
+fn find_station(station_query: &Query<(Entity, &GroundStation)>, name: &str) -> Option<Entity> {
+    station_query
+        .iter()
+        .find(|(_, station)| station.name == name)
+        .map(|(entity, _)| entity)
+}
+
+pub fn start_recording(mut commands: Commands, campaign: Res<Campaign>, seed: Res<GameSeed>) {
+    let default_state = ActionState::<PlayerAction>::default();
+    commands.insert_resource(Recording {
+        replay: Replay::new(campaign.current_level().path, seed.current),
+        actions: PlayerAction::variants()
+            .map(|action| (action, action_event(action, &default_state)))
+            .collect(),
+        pointer: None,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record(
+    time: Res<Time>,
+    mut recording: ResMut<Recording>,
+    action_query: Query<&ActionState<PlayerAction>>,
+    pointer: Option<Res<Pointer>>,
+    collect_query: Query<(&Present, &CollectPresent), Changed<CollectPresent>>,
+    present_query: Query<&Present>,
+    station_query: Query<(Entity, &GroundStation)>,
+    mut upgrade_events: EventReader<UpgradeStation>,
+    next_game_state: Res<NextState<GameState>>,
+) {
+    let recording = &mut *recording;
+    let mut events = Vec::new();
+
+    for action_state in action_query.iter() {
+        for action in PlayerAction::variants() {
+            let event = action_event(action, action_state);
+            if recording.actions.get(&action) != Some(&event) {
+                recording.actions.insert(action, event.clone());
+                events.push(event);
+            }
+        }
+    }
+
+    if let Some(pointer) = pointer {
+        let event = ReplayEvent::Pointer {
+            position: pointer.position,
+            ray: pointer.ray.map(|ray| (ray.origin, ray.direction)),
+            hit: pointer
+                .hit
+                .map(|entity| target(entity, &present_query, &station_query)),
+        };
+        if recording.pointer.as_ref() != Some(&event) {
+            recording.pointer = Some(event.clone());
+            events.push(event);
+        }
+    }
+
+    for (present, collect) in collect_query.iter() {
+        events.push(ReplayEvent::Collect {
+            present: present.id,
+            station: collect
+                .station
+                .and_then(|entity| station_query.get(entity).ok())
+                .map(|(_, station)| station.name.into()),
+        });
+    }
+
+    for event in upgrade_events.read() {
+        if let Ok((_, station)) = station_query.get(event.station) {
+            events.push(ReplayEvent::Upgrade {
+                station: station.name.into(),
+                upgrade: event.upgrade,
+            });
+        }
+    }
+
+    if let Some(state) = next_game_state.0 {
+        events.push(ReplayEvent::GameState(state));
+    }
+
+    recording.replay.frames.push(Frame {
+        delta: time.delta(),
+        events,
+    });
+}
+
+fn finish_recording(mut commands: Commands, recording: Option<Res<Recording>>) {
+    let Some(recording) = recording else {
+        return;
+    };
+    info!(
+        "Recorded {} frames of {}",
+        recording.replay.frames.len(),
+        recording.replay.level
+    );
+    commands.insert_resource(LastReplay(recording.replay.clone()));
+    commands.remove_resource::<Recording>();
+}
+
+// Applies the pointer and actions recorded for the current frame
+fn apply_input(
+    playback: &mut Playback,
+    mut pointer: Option<&mut Pointer>,
+    action_query: &mut Query<&mut ActionState<PlayerAction>>,
+) {
+    for event in playback.replay.frames[playback.frame].events.iter() {
+        match *event {
+            ReplayEvent::Action {
+                action,
+                pressed,
+                value,
+                axis_pair,
+            } => {
+                for mut action_state in action_query.iter_mut() {
+                    if pressed && !action_state.pressed(action) {
+                        action_state.press(action);
+                    } else if !pressed && action_state.pressed(action) {
+                        action_state.release(action);
+                    }
+                    let data = action_state.action_data_mut(action);
+                    data.value = value;
+                    data.axis_pair = axis_pair.map(DualAxisData::from_xy);
+                }
+            }
+            ReplayEvent::Pointer {
+                position,
+                ray,
+                ref hit,
+            } => {
+                if let Some(pointer) = pointer.as_deref_mut() {
+                    pointer.position = position;
+                    pointer.ray = ray.map(|(origin, direction)| Ray { origin, direction });
+                }
+                playback.hit = hit.clone();
+            }
+            _ => {}
+        }
+    }
+}
+
+// The first frame starts part way through, after the player's input has been added
+fn start_playback(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    pointer: Option<ResMut<Pointer>>,
+    mut action_query: Query<&mut ActionState<PlayerAction>>,
+    player_query: Query<Entity, With<Player>>,
+) {
+    info!("Playing back {}", playback.replay.level);
+    commands.insert_resource(PlayedBack);
+
+    // Nothing the player does should get mixed in with the replay
+    for player in player_query.iter() {
+        commands.entity(player).remove::<InputMap<PlayerAction>>();
+    }
+
+    if !playback.replay.frames.is_empty() {
+        apply_input(
+            &mut playback,
+            pointer.map(|p| p.into_inner()),
+            &mut action_query,
+        );
+    }
+}
+
+fn play_input(
+    mut playback: ResMut<Playback>,
+    pointer: Option<ResMut<Pointer>>,
+    mut action_query: Query<&mut ActionState<PlayerAction>>,
+) {
+    if playback.frame < playback.replay.frames.len() {
+        apply_input(
+            &mut playback,
+            pointer.map(|p| p.into_inner()),
+            &mut action_query,
+        );
+    }
+}
+
+fn play_commands(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    present_query: Query<(Entity, &Present)>,
+    station_query: Query<(Entity, &GroundStation)>,
+    mut upgrade_events: EventWriter<UpgradeStation>,
+) {
+    let mut missing = false;
+    for event in playback.events() {
+        match event {
+            ReplayEvent::Collect { present, station } => {
+                let entity = present_query
+                    .iter()
+                    .find(|(_, p)| p.id == *present)
+                    .map(|(entity, _)| entity);
+                let station = station
+                    .as_ref()
+                    .and_then(|name| find_station(&station_query, name));
+                match entity {
+                    Some(entity) => {
+                        commands.entity(entity).insert(CollectPresent { station });
+                    }
+                    None => missing = true,
+                }
+            }
+            ReplayEvent::Upgrade { station, upgrade } => {
+                match find_station(&station_query, station) {
+                    Some(station) => upgrade_events.send(UpgradeStation {
+                        station,
+                        upgrade: *upgrade,
+                    }),
+                    None => missing = true,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if missing && !playback.diverged {
+        warn!(
+            "Replay diverged on frame {}: a present or station is gone",
+            playback.frame
+        );
+        playback.diverged = true;
+    }
+}
+
+// Runs after everything else so pausing from the pause menu is replayed too
+fn play_state(playback: Res<Playback>, mut next_game_state: ResMut<NextState<GameState>>) {
+    for event in playback.events() {
+        if let ReplayEvent::GameState(state) = event {
+            next_game_state.set(*state);
+        }
+    }
+}
+
+// Warns once if the pointer is on something other than in the recording
+fn check(
+    mut playback: ResMut<Playback>,
+    pointer: Option<Res<Pointer>>,
+    present_query: Query<&Present>,
+    station_query: Query<(Entity, &GroundStation)>,
+) {
+    let Some(pointer) = pointer else {
+        return;
+    };
+    let hit = pointer
+        .hit
+        .map(|entity| target(entity, &present_query, &station_query));
+    if hit != playback.hit && !playback.diverged {
+        warn!(
+            "Replay diverged on frame {}: pointing at {:?} rather than {:?}",
+            playback.frame, hit, playback.hit
+        );
+        playback.diverged = true;
+    }
+}
+
+// Moves to the next frame and gives it the same length it had when recorded
+fn advance(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    state: Res<State<AppState>>,
+    bindings: Option<Res<Bindings>>,
+    player_query: Query<Entity, (With<Player>, With<ActionState<PlayerAction>>)>,
+) {
+    // Frames are counted from the start of the level. Loading frames use the first frame's length
+    if *state.get() == AppState::InGame {
+        playback.frame += 1;
+    }
+
+    if let Some(frame) = playback.replay.frames.get(playback.frame) {
+        let previous = std::mem::replace(
+            &mut *strategy,
+            TimeUpdateStrategy::ManualDuration(frame.delta),
+        );
+        playback.strategy.get_or_insert(previous);
+        return;
+    }
+
+    info!("Replay finished");
+    // Hand back to the player
+    if let Some(bindings) = bindings {
+        for player in player_query.iter() {
+            commands.entity(player).insert(bindings.0.clone());
+        }
+    }
+    stop(&mut commands, &mut playback, &mut strategy);
+}
+
+fn end_playback(
+    mut commands: Commands,
+    playback: Option<ResMut<Playback>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+) {
+    if let Some(mut playback) = playback {
+        info!("Replay stopped on frame {}", playback.frame);
+        stop(&mut commands, &mut playback, &mut strategy);
+    }
+}
+
+fn forget_played_back(mut commands: Commands) {
+    commands.remove_resource::<PlayedBack>();
+}
+
+fn stop(commands: &mut Commands, playback: &mut Playback, strategy: &mut TimeUpdateStrategy) {
+    if let Some(previous) = playback.strategy.take() {
+        *strategy = previous;
+    }
+    commands.remove_resource::<Playback>();
+}
+
+// Keeps the last few replays next to the save so they can be attached to bug reports
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(last_replay: Option<Res<LastReplay>>) {
+    let Some(last_replay) = last_replay.filter(|last_replay| last_replay.is_changed()) else {
+        return;
+    };
+    let Some(dir) = replay_dir() else {
+        return;
+    };
+
+    let name = format!(
+        "{}.replay.ron",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    );
+    let result = std::fs::create_dir_all(&dir)
+        .map_err(ReplayError::from)
+        .and_then(|_| last_replay.0.to_ron())
+        .and_then(|text| Ok(std::fs::write(dir.join(&name), text)?));
+    match result {
+        Ok(()) => info!("Saved replay to {}", dir.join(&name).display()),
+        Err(e) => error!("{}", e),
+    }
+
+    prune(&dir);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_dir() -> Option<std::path::PathBuf> {
+    directories::ProjectDirs::from("", "", "sfu").map(|dirs| dirs.data_dir().join("replays"))
+}
+
+// Names start with the time they were saved so the oldest sort first
+#[cfg(not(target_arch = "wasm32"))]
+fn prune(dir: &std::path::Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".replay.ron"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths.iter().take(paths.len().saturating_sub(KEEP)) {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Could not delete old replay: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(LEVELS[1].path, 1234);
+        replay.frames = vec![
+            Frame {
+                delta: Duration::from_nanos(16_666_667),
+                events: vec![
+                    ReplayEvent::Action {
+                        action: PlayerAction::Move,
+                        pressed: true,
+                        value: 0.1,
+                        axis_pair: Some(Vec2::new(0.1, -3.3)),
+                    },
+                    ReplayEvent::Pointer {
+                        position: Some(Vec2::new(600.0, 400.0)),
+                        ray: Some((Vec3::Z * 80.0, Vec3::NEG_Z)),
+                        hit: Some(Target::Present(12)),
+                    },
+                ],
+            },
+            Frame {
+                delta: Duration::from_millis(7),
+                events: Vec::new(),
+            },
+            Frame {
+                delta: Duration::from_millis(250),
+                events: vec![
+                    ReplayEvent::Collect {
+                        present: 12,
+                        station: Some("Cape Canaveral".into()),
+                    },
+                    ReplayEvent::Upgrade {
+                        station: "Baikonur".into(),
+                        upgrade: Upgrade::Cargo,
+                    },
+                    ReplayEvent::GameState(GameState::Paused),
+                ],
+            },
+        ];
+        replay
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = replay();
+
+        let loaded = Replay::parse(&replay.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.level_index().unwrap(), 1);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut replay = replay();
+        replay.version = VERSION + 1;
+
+        assert!(matches!(
+            Replay::parse(&replay.to_ron().unwrap()),
+            Err(ReplayError::Version(version)) if version == VERSION + 1
+        ));
+        assert!(Replay::parse("not a replay").is_err());
+    }
+
+    #[test]
+    fn unknown_levels_can_not_be_played() {
+        let mut replay = replay();
+        replay.level = "levels/99.level.ron".into();
+
+        assert!(matches!(
+            Playback::new(replay),
+            Err(ReplayError::UnknownLevel(_))
+        ));
+    }
+}
@@ -0,0 +1,422 @@
+use bevy::prelude::*;
+use bevy::utils::thiserror;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::campaign::{Campaign, LevelRecord, LEVELS};
+use crate::input::Bindings;
+use crate::settings::Settings;
+
+/// Version written to new saves. When the format changes keep the old struct around, bump
+/// this and add a step converting the old struct to the new one in `AnySave::migrate`
+const VERSION: u32 = 2;
+
+#[cfg(not(target_arch = "wasm32"))]
+const FILE_NAME: &str = "save.ron";
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "sfu.save";
+
+// Keeps progress and settings between sessions
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, load)
+            .add_systems(PostUpdate, save_on_change);
+    }
+}
+
+/// Everything kept between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    pub unlocked: usize,
+    pub levels: Vec<LevelSave>, // Keyed by path so levels can be reordered without losing scores
+    pub settings: Settings,
+    pub bindings: Bindings,
+}
+
+// Read first to find out how to read the rest. Every other field is ignored
+#[derive(Deserialize)]
+struct SaveHeader {
+    #[serde(default = "first_version")]
+    version: u32,
+}
+
+// The game has always written a version so a save without one was edited by hand. Read it as
+// the oldest format rather than the newest so it still goes through every upgrade
+fn first_version() -> u32 {
+    1
+}
+
+/// Save from version 1, before controls could be rebound
+#[derive(Deserialize)]
+#[serde(default)]
+struct SaveV1 {
+    unlocked: usize,
+    levels: Vec<LevelSave>,
+    settings: Settings,
+}
+
+impl Default for SaveV1 {
+    fn default() -> Self {
+        Self {
+            unlocked: 1,
+            levels: Vec::new(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+// A save in any format the game has written
+enum AnySave {
+    V1(SaveV1),
+    V2(SaveData),
+}
+
+impl AnySave {
+    fn parse(text: &str) -> Result<Self, SaveError> {
+        let header = ron::from_str::<SaveHeader>(text)?;
+        match header.version {
+            1 => Ok(AnySave::V1(ron::from_str(text)?)),
+            2 => Ok(AnySave::V2(ron::from_str(text)?)),
+            version if version > VERSION => Err(SaveError::TooNew(version)),
+            version => Err(SaveError::UnknownVersion(version)),
+        }
+    }
+
+    // Upgrades a version at a time until it is in the current format
+    fn migrate(mut self) -> SaveData {
+        loop {
+            self = match self {
+                AnySave::V1(save) => AnySave::V2(SaveData {
+                    version: 2,
+                    unlocked: save.unlocked,
+                    levels: save.levels,
+                    settings: save.settings,
+                    bindings: Bindings::default(),
+                }),
+                AnySave::V2(save) => return save,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelSave {
+    pub path: String,
+    pub score: i32,
+    pub stars: usize,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            unlocked: 1,
+            levels: Vec::new(),
+            settings: Settings::default(),
+            bindings: Bindings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not read save: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse save: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("Could not write save: {0}")]
+    Format(#[from] ron::Error),
+    #[error("Save is from a newer version ({0})")]
+    TooNew(u32),
+    #[error("Save version {0} is not supported")]
+    UnknownVersion(u32),
+    #[error("Could not access storage: {0}")]
+    Storage(String),
+}
+
+impl SaveData {
+    pub fn new(campaign: &Campaign, settings: &Settings, bindings: &Bindings) -> Self {
+        Self {
+            unlocked: campaign.unlocked,
+            levels: LEVELS
+                .iter()
+                .zip(campaign.records.iter())
+                .filter(|(_, record)| record.score > 0 || record.stars > 0)
+                .map(|(level, record)| LevelSave {
+                    path: level.path.into(),
+                    score: record.score,
+                    stars: record.stars,
+                })
+                .collect(),
+            settings: settings.clone(),
+            bindings: bindings.clone(),
+            ..default()
+        }
+    }
+
+    pub fn apply(&self, campaign: &mut Campaign, settings: &mut Settings, bindings: &mut Bindings) {
+        campaign.unlocked = self.unlocked.clamp(1, LEVELS.len());
+        for (level, record) in LEVELS.iter().zip(campaign.records.iter_mut()) {
+            *record = self
+                .levels
+                .iter()
+                .find(|save| save.path == level.path)
+                .map(|save| LevelRecord {
+                    score: save.score,
+                    stars: save.stars.min(3),
+                })
+                .unwrap_or_default();
+        }
+        *settings = self.settings.clone();
+        *bindings = self.bindings.clone().with_defaults();
+    }
+
+    /// Parses a save written by any version of the game, upgrading it to the current format
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        Ok(AnySave::parse(text)?.migrate())
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_path() -> Option<std::path::PathBuf> {
+    directories::ProjectDirs::from("", "", "sfu").map(|dirs| dirs.config_dir().join(FILE_NAME))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> Result<Option<String>, SaveError> {
+    let Some(path) = save_path() else {
+        return Ok(None);
+    };
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(text: &str) -> Result<(), SaveError> {
+    let Some(path) = save_path() else {
+        return Err(SaveError::Storage("no config directory".into()));
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write to the side first so a crash half way through cant corrupt the old save
+    let tmp = path.with_extension("ron.tmp");
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+// Keep a copy of a save we could not read so the player does not lose it for good
+#[cfg(not(target_arch = "wasm32"))]
+fn backup(text: &str) {
+    if let Some(path) = save_path() {
+        if let Err(e) = std::fs::write(path.with_extension("ron.bak"), text) {
+            warn!("Could not back up save: {}", e);
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Result<web_sys::Storage, SaveError> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| SaveError::Storage("localStorage is not available".into()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read() -> Result<Option<String>, SaveError> {
+    storage()?
+        .get_item(STORAGE_KEY)
+        .map_err(|e| SaveError::Storage(format!("{:?}", e)))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(text: &str) -> Result<(), SaveError> {
+    storage()?
+        .set_item(STORAGE_KEY, text)
+        .map_err(|e| SaveError::Storage(format!("{:?}", e)))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn backup(text: &str) {
+    if let Ok(storage) = storage() {
+        let _ = storage.set_item(&format!("{}.bak", STORAGE_KEY), text);
+    }
+}
+
+fn load(
+    mut campaign: ResMut<Campaign>,
+    mut settings: ResMut<Settings>,
+    mut bindings: ResMut<Bindings>,
+) {
+    let text = match read() {
+        Ok(Some(text)) => text,
+        Ok(None) => {
+            info!("No save found. Starting fresh");
+            return;
+        }
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+
+    match SaveData::parse(&text) {
+        Ok(save) => {
+            save.apply(&mut campaign, &mut settings, &mut bindings);
+            info!("Loaded save");
+        }
+        Err(e) => {
+            warn!("{}. Starting fresh", e);
+            backup(&text);
+        }
+    }
+}
+
+fn save_on_change(campaign: Res<Campaign>, settings: Res<Settings>, bindings: Res<Bindings>) {
+    // Skip the first frame where everything counts as changed
+    if campaign.is_added()
+        || !(campaign.is_changed() || settings.is_changed() || bindings.is_changed())
+    {
+        return;
+    }
+
+    let result = SaveData::new(&campaign, &settings, &bindings)
+        .to_ron()
+        .and_then(|text| write(&text));
+    if let Err(e) = result {
+        error!("Could not save: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::FrameLimit;
+
+    #[test]
+    fn round_trip() {
+        let mut campaign = Campaign {
+            unlocked: 2,
+            ..default()
+        };
+        campaign.records[0] = LevelRecord {
+            score: 12000,
+            stars: 2,
+        };
+        let settings = Settings {
+            vsync: false,
+            frame_limit: FrameLimit::Fps(60),
+            ui_scale: 1.25,
+            ..default()
+        };
+        let save = SaveData::new(&campaign, &settings, &Bindings::default());
+
+        let loaded = SaveData::parse(&save.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded, save);
+
+        let mut restored = Campaign::default();
+        let mut restored_settings = Settings::default();
+        loaded.apply(
+            &mut restored,
+            &mut restored_settings,
+            &mut Bindings::default(),
+        );
+        assert_eq!(restored_settings, settings);
+        assert_eq!(restored.unlocked, 2);
+        assert_eq!(restored.records[0].score, 12000);
+        assert_eq!(restored.records[0].stars, 2);
+        assert_eq!(restored.records[1].score, 0);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let save = SaveData::parse("(unlocked: 3)").unwrap();
+
+        assert_eq!(save.unlocked, 3);
+        assert_eq!(save.version, VERSION);
+        assert!(save.levels.is_empty());
+    }
+
+    #[test]
+    fn corrupted_save_is_an_error() {
+        assert!(SaveData::parse("(unlocked: 3, levels: [(path: ").is_err());
+        assert!(SaveData::parse("not a save").is_err());
+        assert!(SaveData::parse("(unlocked: \"three\")").is_err());
+        assert!(SaveData::parse("(version: 0, unlocked: 3)").is_err());
+    }
+
+    #[test]
+    fn version_1_saves_are_upgraded() {
+        let save = SaveData::parse(
+            "(version: 1, unlocked: 2, \
+             levels: [(path: \"levels/01.level.ron\", score: 900, stars: 1)], \
+             settings: (vsync: false))",
+        )
+        .unwrap();
+
+        assert_eq!(save.version, VERSION);
+        assert_eq!(save.unlocked, 2);
+        assert_eq!(
+            save.levels,
+            vec![LevelSave {
+                path: "levels/01.level.ron".into(),
+                score: 900,
+                stars: 1,
+            }]
+        );
+        assert!(!save.settings.vsync);
+        assert_eq!(save.bindings, Bindings::default());
+    }
+
+    #[test]
+    fn saves_without_a_version_are_read_as_the_first_version() {
+        let save = SaveData::parse("(unlocked: 2, settings: (vsync: false))").unwrap();
+
+        assert_eq!(save.version, VERSION);
+        assert_eq!(save.unlocked, 2);
+        assert!(!save.settings.vsync);
+        assert_eq!(save.bindings, Bindings::default());
+    }
+
+    #[test]
+    fn newer_save_is_refused() {
+        let text = format!("(version: {}, unlocked: 3)", VERSION + 1);
+
+        assert!(matches!(
+            SaveData::parse(&text),
+            Err(SaveError::TooNew(version)) if version == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let save = SaveData::parse(
+            "(unlocked: 99, levels: [(path: \"levels/01.level.ron\", score: 5, stars: 9)])",
+        )
+        .unwrap();
+        let mut campaign = Campaign::default();
+        save.apply(
+            &mut campaign,
+            &mut Settings::default(),
+            &mut Bindings::default(),
+        );
+
+        assert_eq!(campaign.unlocked, LEVELS.len());
+        assert_eq!(campaign.records[0].stars, 3);
+    }
+}
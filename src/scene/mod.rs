@@ -1,15 +1,27 @@
 use bevy::prelude::*;
 
-use crate::{asset::LoadingAssets, despawn, state::AppState, Coord};
+use crate::{
+    asset::{LoadError, LoadingAssets},
+    despawn,
+    game::{ground_station, interpolation::Interpolated},
+    state::AppState,
+    Coord,
+};
 
+// Loads the models and lights. Not needed to simulate the game
 pub struct ScenePlugin;
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(OnEnter(AppState::Loading), load_resources)
-            .add_systems(OnExit(AppState::Loading), setup)
-            .add_systems(Update, update_coords)
-            .add_systems(OnExit(AppState::InGame), despawn::<SceneElement>);
+        app.add_systems(
+            OnEnter(AppState::Loading),
+            (load_resources, ground_station::load_resources),
+        )
+        .add_systems(
+            OnExit(AppState::Loading),
+            setup.run_if(not(resource_exists::<LoadError>())),
+        )
+        .add_systems(OnExit(AppState::InGame), despawn::<SceneElement>);
     }
 }
 
@@ -58,7 +70,8 @@ fn setup(mut commands: Commands, scene_res: Res<SceneResources>) {
     info!("Setting up game");
 }
 
-pub fn update_coords(mut query: Query<(&mut Transform, &Coord)>) {
+// Things moved by the gameplay steps place themselves
+pub fn update_coords(mut query: Query<(&mut Transform, &Coord), Without<Interpolated>>) {
     for (mut transform, coord) in query.iter_mut() {
         coord.apply(&mut transform);
     }
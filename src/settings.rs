@@ -0,0 +1,112 @@
+use bevy::{prelude::*, window::PresentMode, window::PrimaryWindow};
+use bevy_framepace::{FramepaceSettings, Limiter};
+use serde::{Deserialize, Serialize};
+
+// Options the settings menu cycles through
+#[cfg(not(target_arch = "wasm32"))]
+pub const MSAA_OPTIONS: [u32; 4] = [1, 2, 4, 8];
+#[cfg(target_arch = "wasm32")]
+pub const MSAA_OPTIONS: [u32; 2] = [1, 4]; // WebGL2 only supports 4x
+pub const FRAME_LIMIT_OPTIONS: [FrameLimit; 5] = [
+    FrameLimit::Auto,
+    FrameLimit::Fps(30),
+    FrameLimit::Fps(60),
+    FrameLimit::Fps(120),
+    FrameLimit::Off,
+];
+pub const SENSITIVITY_OPTIONS: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+pub const ZOOM_SPEED_OPTIONS: [f32; 4] = [0.5, 1.0, 1.5, 2.0];
+pub const UI_SCALE_OPTIONS: [f64; 4] = [0.75, 1.0, 1.25, 1.5];
+
+// Applies the player's settings to the engine
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>().add_systems(Update, apply);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FrameLimit {
+    Auto, // Match the monitor refresh rate
+    Fps(u32),
+    Off,
+}
+
+/// Player preferences kept in the save file
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub msaa_samples: u32,
+    pub vsync: bool,
+    pub frame_limit: FrameLimit,
+    pub camera_sensitivity: f32,
+    pub zoom_speed: f32,
+    pub ui_scale: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 4,
+            vsync: true,
+            frame_limit: FrameLimit::Auto,
+            camera_sensitivity: 1.0,
+            zoom_speed: 1.0,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn msaa(&self) -> Msaa {
+        match self.msaa_samples {
+            0 | 1 => Msaa::Off,
+            2 => Msaa::Sample2,
+            8 => Msaa::Sample8,
+            _ => Msaa::Sample4,
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    pub fn limiter(&self) -> Limiter {
+        match self.frame_limit {
+            FrameLimit::Auto => Limiter::Auto,
+            FrameLimit::Fps(fps) => Limiter::from_framerate(fps.max(1) as f64),
+            FrameLimit::Off => Limiter::Off,
+        }
+    }
+}
+
+/// Picks the option after `current`, wrapping back to the first one
+pub fn next_option<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let index = options.iter().position(|o| *o == current);
+    options[index.map_or(0, |i| (i + 1) % options.len())]
+}
+
+fn apply(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut framepace: ResMut<FramepaceSettings>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    commands.insert_resource(settings.msaa());
+    for mut window in windows.iter_mut() {
+        window.present_mode = settings.present_mode();
+    }
+    framepace.limiter = settings.limiter();
+    ui_scale.0 = settings.ui_scale;
+}
@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use bevy::{asset::AssetMetaCheck, prelude::*, time::TimeUpdateStrategy};
+
+use crate::asset::{LoadError, LoadingAssets};
+use crate::game::{
+    campaign::{Campaign, LEVELS},
+    present::{CollectPresent, Present},
+    GameData, GameSeed,
+};
+use crate::replay::{LastReplay, Playback, Replay, ReplayError};
+use crate::state::AppState;
+use crate::SimulationPlugin;
+
+/// Length of every simulated frame in seconds unless changed with
+/// [`Simulation::set_frame_rate`]. Same as a gameplay step
+pub const STEP: f32 = 1.0 / 60.0;
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stand-in for `DefaultPlugins` without a window, renderer or model loaders. Time advances by
+/// exactly [`STEP`] every update so runs are repeatable
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AssetMetaCheck::Never)
+            .add_plugins((
+                MinimalPlugins,
+                AssetPlugin::default(),
+                TransformPlugin,
+                HierarchyPlugin,
+                bevy::scene::ScenePlugin, // Physics looks for scenes to build colliders from
+            ))
+            // Presents are still spawned with a mesh and material even though nothing draws them
+            .init_asset::<Mesh>()
+            .init_asset::<StandardMaterial>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                STEP,
+            )));
+    }
+}
+
+/// Runs levels without a window so they can be played by code and checked in tests
+pub struct Simulation {
+    pub app: App,
+    frame: f32, // Seconds each update advances time by
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        let mut app = App::new();
+        app.add_plugins((HeadlessPlugin, SimulationPlugin))
+            .insert_resource(GameSeed::fixed(seed));
+        app.update(); // Run the startup systems
+
+        Self { app, frame: STEP }
+    }
+
+    /// Plays back a recorded game from the start of its level
+    pub fn replay(replay: Replay) -> Result<Self, ReplayError> {
+        let playback = Playback::new(replay)?;
+        let mut sim = Self::new(playback.seed());
+        let level = playback.level();
+        sim.app.insert_resource(playback);
+        sim.start_level(level);
+        Ok(sim)
+    }
+
+    /// Loads a level and waits until it has started. Locked levels can be played too
+    pub fn start_level(&mut self, index: usize) {
+        assert!(index < LEVELS.len(), "there is no level {}", index);
+        self.app.world.resource_mut::<Campaign>().current = index;
+        self.app.insert_resource(LoadingAssets::default());
+        self.app
+            .world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Loading);
+
+        // Levels are read from disk on another thread so this waits in real time
+        let started = Instant::now();
+        while self.state() != AppState::InGame {
+            assert!(
+                started.elapsed() < LOAD_TIMEOUT,
+                "level {} did not load",
+                LEVELS[index].path
+            );
+            self.app.update();
+            if let Some(LoadError(error)) = self.app.world.get_resource::<LoadError>() {
+                panic!("{}", error);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Runs updates as if the game was drawn `fps` times a second. Gameplay should come out
+    /// the same at any rate
+    pub fn set_frame_rate(&mut self, fps: f32) {
+        self.frame = 1.0 / fps;
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                self.frame,
+            )));
+    }
+
+    /// Advances the game by `seconds`, rounded up to whole frames
+    pub fn step(&mut self, seconds: f32) {
+        for _ in 0..(seconds / self.frame).ceil() as usize {
+            self.app.update();
+        }
+    }
+
+    /// Steps until `done` returns true or `seconds` have passed. Returns whether it finished
+    pub fn run_until(&mut self, seconds: f32, mut done: impl FnMut(&mut Self) -> bool) -> bool {
+        for _ in 0..(seconds / self.frame).ceil() as usize {
+            if done(self) {
+                return true;
+            }
+            self.app.update();
+        }
+        done(self)
+    }
+
+    pub fn state(&self) -> AppState {
+        *self.app.world.resource::<State<AppState>>().get()
+    }
+
+    pub fn game_data(&self) -> &GameData {
+        self.app.world.resource::<GameData>()
+    }
+
+    /// Recording of the last level played
+    pub fn last_replay(&self) -> Option<&Replay> {
+        self.app
+            .world
+            .get_resource::<LastReplay>()
+            .map(|last_replay| &last_replay.0)
+    }
+
+    /// Every present still floating around or being carried
+    pub fn presents(&mut self) -> Vec<Entity> {
+        let mut query = self.app.world.query_filtered::<Entity, With<Present>>();
+        query.iter(&self.app.world).collect()
+    }
+
+    /// Marks a present for collection like clicking it does
+    pub fn mark(&mut self, present: Entity, station: Option<Entity>) {
+        self.app
+            .world
+            .entity_mut(present)
+            .insert(CollectPresent { station });
+    }
+}
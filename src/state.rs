@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Component to tag an entity as only needed in some of the states
 #[derive(Component, Debug)]
@@ -16,7 +17,7 @@ pub enum AppState {
     PostGame,
 }
 
-#[derive(States, Debug, Copy, Clone, Hash, Eq, PartialEq, Default)]
+#[derive(States, Debug, Copy, Clone, Hash, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub enum GameState {
     #[default]
     Running,
@@ -28,4 +29,5 @@ pub enum InteractionState {
     #[default]
     Idle,
     OnEntity,
+    Selecting, // Dragging out a selection box
 }
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+
+use crate::asset::{LoadError, LoadingAssets};
+use crate::despawn;
+use crate::game::auto_player::AutoPlayer;
+use crate::replay::{self, Recording};
+use crate::state::{AppState, ForState, GameState};
+
+use super::assets::UiAssets;
+use super::start::StartMenuState;
+
+const IDLE_TIME: f32 = 30.0; // Seconds on the main menu without input before the demo starts
+const RESULTS_TIME: f32 = 5.0; // Seconds the results stay up before going back to the menu
+
+// This plugin lets the computer play a level as a demo when the start menu is left alone
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            start_demo
+                .run_if(in_state(AppState::StartMenu))
+                .run_if(in_state(StartMenuState::Main)),
+        )
+        .add_systems(
+            OnEnter(AppState::InGame),
+            setup
+                .after(replay::start_recording)
+                .run_if(resource_exists::<Demo>()),
+        )
+        .add_systems(OnExit(AppState::InGame), despawn::<DemoScreen>)
+        .add_systems(
+            OnExit(AppState::Loading),
+            stop_demo.run_if(resource_exists::<LoadError>()),
+        )
+        .add_systems(PostUpdate, end_demo.run_if(resource_exists::<Demo>()));
+    }
+}
+
+/// Exists while the demo is running
+#[derive(Resource)]
+pub struct Demo;
+
+#[derive(Component)]
+struct DemoScreen;
+
+fn any_input(
+    keys: &Input<KeyCode>,
+    mouse_buttons: &Input<MouseButton>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> bool {
+    keys.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || gamepad_buttons.get_just_pressed().next().is_some()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_demo(
+    mut commands: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut idle: Local<f32>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<StartMenuState>>,
+) {
+    if cursor_moved.read().count() > 0 || any_input(&keys, &mouse_buttons, &gamepad_buttons) {
+        *idle = 0.0;
+        return;
+    }
+
+    *idle += time.delta_seconds();
+    if *idle < IDLE_TIME {
+        return;
+    }
+    *idle = 0.0;
+
+    // Plays whichever level was picked last
+    commands.insert_resource(AutoPlayer::default());
+    commands.insert_resource(Demo);
+    commands.insert_resource(LoadingAssets::default());
+    app_state.set(AppState::Loading);
+    menu_state.set(StartMenuState::Disabled);
+}
+
+fn setup(mut commands: Commands, assets: Res<UiAssets>) {
+    // Nobody needs a replay of the demo
+    commands.remove_resource::<Recording>();
+
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                bottom: Val::Px(10.0),
+                left: Val::Px(10.0),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            text: Text::from_section(
+                "demo - press any key",
+                TextStyle {
+                    font: assets.font.clone(),
+                    font_size: 40.0,
+                    color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                },
+            ),
+            ..default()
+        },
+        ForState {
+            states: vec![AppState::InGame],
+        },
+        DemoScreen,
+    ));
+}
+
+// Runs after everything else so it has the final say on where to go next
+#[allow(clippy::too_many_arguments)]
+fn end_demo(
+    commands: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    state: Res<State<AppState>>,
+    mut results: Local<f32>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    match state.get() {
+        AppState::InGame => {}
+        AppState::PostGame => *results += time.delta_seconds(),
+        // Leaving half way through loading would strand the level
+        _ => return,
+    }
+
+    if *results < RESULTS_TIME && !any_input(&keys, &mouse_buttons, &gamepad_buttons) {
+        return;
+    }
+    *results = 0.0;
+
+    stop_demo(commands);
+    app_state.set(AppState::StartMenu);
+    game_state.set(GameState::Running);
+}
+
+fn stop_demo(mut commands: Commands) {
+    commands.remove_resource::<AutoPlayer>();
+    commands.remove_resource::<Demo>();
+}
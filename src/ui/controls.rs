@@ -0,0 +1,348 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+};
+use leafwing_input_manager::prelude::*;
+
+use crate::despawn;
+use crate::game::PlayerAction;
+use crate::input::{input_label, Bindings};
+use crate::state::{AppState, ForState};
+
+use super::assets::UiAssets;
+use super::start::StartMenuState;
+
+const MOTION_THRESHOLD: f32 = 20.0; // Mouse has to move this far in a frame to bind to it
+const STICK_THRESHOLD: f32 = 0.5; // Sticks have to be pushed this far to bind to them
+
+// This plugin manages the screen for changing key bindings
+pub struct ControlsMenuPlugin;
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(StartMenuState::Controls), setup)
+            .add_systems(
+                OnExit(StartMenuState::Controls),
+                (despawn::<ControlsScreen>, cancel),
+            )
+            .add_systems(
+                Update,
+                (menu_action, capture.after(menu_action), update_labels)
+                    .run_if(in_state(StartMenuState::Controls)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct ControlsScreen;
+
+// Every action with the name shown on the controls screen
+const ACTIONS: [(PlayerAction, &str); 11] = [
+    (PlayerAction::CanMove, "drag and click"),
+    (PlayerAction::Move, "look around"),
+    (PlayerAction::Zoom, "zoom"),
+    (PlayerAction::OrbitAxis, "orbit"),
+    (PlayerAction::ZoomAxis, "zoom axis"),
+    (PlayerAction::Aim, "aim"),
+    (PlayerAction::ResetCamera, "reset camera"),
+    (PlayerAction::Focus, "focus camera"),
+    (PlayerAction::Pause, "pause"),
+    (PlayerAction::CycleStation, "next station"),
+    (PlayerAction::Select, "box select"),
+];
+
+// Action waiting for the player to press something
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<PlayerAction>,
+    armed: bool, // Set once the click that started the rebind has been let go
+    message: String,
+}
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+enum MenuButtonAction {
+    Rebind(PlayerAction),
+    Reset,
+    Back,
+}
+
+#[derive(Component)]
+struct BindingText(PlayerAction);
+
+#[derive(Component)]
+struct MessageText;
+
+fn is_axis(action: PlayerAction) -> bool {
+    matches!(
+        action,
+        PlayerAction::Move
+            | PlayerAction::Zoom
+            | PlayerAction::OrbitAxis
+            | PlayerAction::ZoomAxis
+            | PlayerAction::Aim
+    )
+}
+
+fn is_dual_axis(action: PlayerAction) -> bool {
+    matches!(
+        action,
+        PlayerAction::Move | PlayerAction::OrbitAxis | PlayerAction::Aim
+    )
+}
+
+fn setup(mut commands: Commands, assets: Res<UiAssets>, app_state: Res<State<AppState>>) {
+    let text_style = |font_size: f32| TextStyle {
+        font: assets.font.clone(),
+        font_size,
+        color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            ForState {
+                states: vec![AppState::StartMenu],
+            },
+            ControlsScreen,
+        ))
+        .with_children(|parent| {
+            // Keep the game visible behind the menu when opened from the pause menu
+            if *app_state == AppState::StartMenu {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        ..default()
+                    },
+                    image: assets.background.clone(),
+                    ..default()
+                });
+            }
+            parent.spawn(TextBundle::from_section("Controls", text_style(80.0)));
+            for (action, _) in ACTIONS {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        MenuButtonAction::Rebind(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section("", text_style(30.0)),
+                            BindingText(action),
+                        ));
+                    });
+            }
+            parent.spawn((TextBundle::from_section("", text_style(25.0)), MessageText));
+            for (label, action) in [
+                ("reset", MenuButtonAction::Reset),
+                ("back", MenuButtonAction::Back),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, text_style(50.0)));
+                    });
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_labels(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    mut binding_query: Query<(&mut Text, &BindingText), Without<MessageText>>,
+    mut message_query: Query<&mut Text, With<MessageText>>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (mut text, BindingText(action)) in binding_query.iter_mut() {
+        let name = ACTIONS
+            .iter()
+            .find(|(a, _)| a == action)
+            .map_or("", |(_, name)| name);
+        let inputs = if rebinding.action == Some(*action) {
+            "...".to_string()
+        } else {
+            bindings
+                .0
+                .get(*action)
+                .map(|inputs| inputs.iter().map(input_label).collect::<Vec<_>>())
+                .unwrap_or_default()
+                .join(" / ")
+        };
+        if let Some(text) = text.sections.first_mut() {
+            text.value = format!("{}: {}", name, inputs);
+        }
+    }
+
+    for mut text in message_query.iter_mut() {
+        if let Some(text) = text.sections.first_mut() {
+            text.value = rebinding.message.clone();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn menu_action(
+    interaction_query: Query<
+        (&Interaction, &MenuButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut menu_state: ResMut<NextState<StartMenuState>>,
+) {
+    // Clicks while waiting for an input are the input
+    if rebinding.action.is_some() {
+        return;
+    }
+
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button_action {
+                MenuButtonAction::Rebind(action) => {
+                    *rebinding = Rebinding {
+                        action: Some(*action),
+                        armed: false,
+                        message: if is_axis(*action) {
+                            "Move the mouse, scroll or move a stick. Escape to cancel".into()
+                        } else {
+                            "Press a key or button. Escape to cancel".into()
+                        },
+                    };
+                }
+                MenuButtonAction::Reset => {
+                    *bindings = Bindings::default();
+                    rebinding.message = "Controls reset".into();
+                }
+                MenuButtonAction::Back => menu_state.set(StartMenuState::Settings),
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn capture(
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let motion = mouse_motion.read().map(|e| e.delta).sum::<Vec2>();
+    let scrolled = mouse_wheel.read().count() > 0;
+
+    let Some(action) = rebinding.action else {
+        return;
+    };
+
+    // Wait for the click on the button to finish so it is not picked up as the new input
+    if !rebinding.armed {
+        if mouse_buttons.get_pressed().len() == 0 {
+            rebinding.armed = true;
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) && action != PlayerAction::Pause {
+        *rebinding = Rebinding::default();
+        return;
+    }
+
+    let input: Option<UserInput> = if is_axis(action) {
+        let dual = is_dual_axis(action);
+        let stick = gamepads.iter().find_map(|gamepad| {
+            [
+                (GamepadAxisType::LeftStickX, DualAxis::left_stick()),
+                (GamepadAxisType::RightStickX, DualAxis::right_stick()),
+                (GamepadAxisType::LeftStickY, DualAxis::left_stick()),
+                (GamepadAxisType::RightStickY, DualAxis::right_stick()),
+            ]
+            .into_iter()
+            .find(|(axis_type, _)| {
+                gamepad_axes
+                    .get(GamepadAxis::new(gamepad, *axis_type))
+                    .is_some_and(|value| value.abs() > STICK_THRESHOLD)
+            })
+        });
+        if let Some((axis_type, dual_axis)) = stick {
+            Some(if dual {
+                dual_axis.into()
+            } else {
+                SingleAxis::symmetric(axis_type, 0.1).into()
+            })
+        } else if scrolled {
+            Some(if dual {
+                DualAxis::mouse_wheel().into()
+            } else {
+                SingleAxis::mouse_wheel_y().into()
+            })
+        } else if motion.length() > MOTION_THRESHOLD {
+            Some(if dual {
+                DualAxis::mouse_motion().into()
+            } else {
+                SingleAxis::mouse_motion_y().into()
+            })
+        } else {
+            None
+        }
+    } else if let Some(key) = keys.get_just_pressed().next() {
+        Some((*key).into())
+    } else if let Some(button) = mouse_buttons.get_just_pressed().next() {
+        Some((*button).into())
+    } else {
+        gamepad_buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| button.button_type.into())
+    };
+
+    let Some(input) = input else {
+        return;
+    };
+
+    if let Some(other) = bindings.conflict(action, &input) {
+        let name = ACTIONS
+            .iter()
+            .find(|(a, _)| *a == other)
+            .map_or("", |(_, name)| name);
+        rebinding.message = format!("{} is already used for {}", input_label(&input), name);
+        return;
+    }
+
+    bindings.rebind(action, input);
+    *rebinding = Rebinding::default();
+}
+
+fn cancel(mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+}
@@ -1,7 +1,12 @@
 use bevy::prelude::*;
 
 use crate::despawn;
+use crate::game::box_select::SelectionBox;
+use crate::game::rocket::{PresentBroken, PresentRefused};
+use crate::game::score::Score;
 use crate::game::{GameData, GameTimer, LevelConfig};
+use crate::input::Reticle;
+use crate::settings::Settings;
 use crate::state::{AppState, ForState};
 
 use super::assets::UiAssets;
@@ -18,6 +23,10 @@ impl Plugin for GamePlugin {
                 (
                     update_countdown.run_if(in_state(AppState::InGame)),
                     update_present_count.run_if(in_state(AppState::InGame)),
+                    update_score.run_if(in_state(AppState::InGame)),
+                    update_message.run_if(in_state(AppState::InGame)),
+                    update_selection_box.run_if(in_state(AppState::InGame)),
+                    update_reticle.run_if(in_state(AppState::InGame)),
                 ),
             );
     }
@@ -32,6 +41,21 @@ struct CountdownText;
 #[derive(Component)]
 struct PresentsText;
 
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct SelectionBoxNode;
+
+#[derive(Component)]
+struct ReticleNode;
+
+const RETICLE_SIZE: f32 = 16.0;
+
+// Short lived feedback shown at the bottom of the screen
+#[derive(Component, Deref, DerefMut)]
+struct MessageText(Timer);
+
 fn setup(mut commands: Commands, assets: Res<UiAssets>) {
     commands
         .spawn((
@@ -94,6 +118,72 @@ fn setup(mut commands: Commands, assets: Res<UiAssets>) {
                 },
                 PresentsText,
             ));
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        top: Val::Px(10.0),
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    text: Text::from_section(
+                        "0",
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 40.0,
+                            color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                        },
+                    ),
+                    ..default()
+                },
+                ScoreText,
+            ));
+            parent.spawn((
+                TextBundle {
+                    style: Style {
+                        bottom: Val::Px(20.0),
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                        },
+                    ),
+                    ..default()
+                },
+                MessageText(Timer::from_seconds(2.0, TimerMode::Once)),
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    border_color: BorderColor(Color::rgb_u8(0xe0, 0x1b, 0x24)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                SelectionBoxNode,
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(RETICLE_SIZE),
+                        height: Val::Px(RETICLE_SIZE),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    border_color: BorderColor(Color::WHITE),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                ReticleNode,
+            ));
         });
 }
 
@@ -119,8 +209,92 @@ fn update_present_count(
             text.value = format!(
                 "\n{}/{}",
                 game_data.presents_collected,
-                level_config.high_orbit_presents + level_config.low_orbit_presents
+                level_config.total_presents()
             );
         }
     }
 }
+
+fn update_score(score: Res<Score>, mut query: Query<&mut Text, With<ScoreText>>) {
+    for mut text in query.iter_mut() {
+        if let Some(text) = text.sections.first_mut() {
+            let multiplier = score.multiplier();
+            text.value = if multiplier > 1.0 {
+                format!("{} x{}", score.total(), multiplier)
+            } else {
+                score.total().to_string()
+            };
+        }
+    }
+}
+
+fn update_message(
+    time: Res<Time>,
+    mut refused_events: EventReader<PresentRefused>,
+    mut broken_events: EventReader<PresentBroken>,
+    mut query: Query<(&mut Text, &mut MessageText)>,
+) {
+    let broken = broken_events.read().count() > 0;
+    let refused = refused_events.read().count() > 0;
+    let message = if broken {
+        Some("Smashed! That rocket was too fast for a fragile present")
+    } else if refused {
+        Some("Out of range! No rocket has the fuel to get there")
+    } else {
+        None
+    };
+
+    for (mut text, mut timer) in query.iter_mut() {
+        if let Some(message) = message {
+            timer.reset();
+            if let Some(text) = text.sections.first_mut() {
+                text.value = message.into();
+            }
+        }
+
+        if timer.tick(time.delta()).just_finished() {
+            if let Some(text) = text.sections.first_mut() {
+                text.value.clear();
+            }
+        }
+    }
+}
+
+fn update_selection_box(
+    selection: Res<SelectionBox>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Style, &mut Visibility), With<SelectionBoxNode>>,
+) {
+    for (mut style, mut visibility) in query.iter_mut() {
+        match selection.rect() {
+            Some(rect) => {
+                // The box is in window pixels but ui pixels get scaled
+                let scale = settings.ui_scale as f32;
+                let rect = Rect::from_corners(rect.min / scale, rect.max / scale);
+                style.left = Val::Px(rect.min.x);
+                style.top = Val::Px(rect.min.y);
+                style.width = Val::Px(rect.width());
+                style.height = Val::Px(rect.height());
+                *visibility = Visibility::Visible;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+fn update_reticle(
+    reticle: Res<Reticle>,
+    settings: Res<Settings>,
+    mut query: Query<(&mut Style, &mut Visibility), With<ReticleNode>>,
+) {
+    for (mut style, mut visibility) in query.iter_mut() {
+        if reticle.active {
+            let position = reticle.position / settings.ui_scale as f32 - RETICLE_SIZE / 2.0;
+            style.left = Val::Px(position.x);
+            style.top = Val::Px(position.y);
+            *visibility = Visibility::Visible;
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
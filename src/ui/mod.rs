@@ -3,14 +3,19 @@ use bevy::prelude::*;
 use crate::state::AppState;
 
 pub mod assets;
+pub mod attract;
 pub mod colors;
+pub mod controls;
 pub mod diagnostics;
 pub mod game;
 pub mod loading;
+pub mod navigation;
 pub mod paused;
 pub mod post;
+pub mod settings;
 pub mod splash;
 pub mod start;
+pub mod station;
 
 pub struct UiPlugin;
 
@@ -0,0 +1,146 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::state::{AppState, GameState};
+
+use super::colors;
+
+// This plugin lets the menus be used with the keyboard or a gamepad by moving a focus
+// between buttons and pressing the focused one
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>()
+            .add_systems(
+                PreUpdate,
+                navigate
+                    .after(UiSystem::Focus)
+                    .run_if(not(in_state(AppState::InGame)).or_else(in_state(GameState::Paused))),
+            )
+            .add_systems(Update, highlight.after(super::button_system));
+    }
+}
+
+#[derive(Resource, Default)]
+struct MenuFocus {
+    focused: Option<Entity>,
+    pressed: Option<Entity>, // Released again on the next frame since no mouse will do it
+}
+
+enum Step {
+    Previous,
+    Next,
+    Confirm,
+}
+
+fn read_step(
+    keys: &Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    gamepads: &Gamepads,
+) -> Option<Step> {
+    let pad = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    if keys.any_just_pressed([KeyCode::Up, KeyCode::Left])
+        || pad(GamepadButtonType::DPadUp)
+        || pad(GamepadButtonType::DPadLeft)
+    {
+        Some(Step::Previous)
+    } else if keys.any_just_pressed([KeyCode::Down, KeyCode::Right])
+        || pad(GamepadButtonType::DPadDown)
+        || pad(GamepadButtonType::DPadRight)
+    {
+        Some(Step::Next)
+    } else if keys.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || pad(GamepadButtonType::South)
+    {
+        Some(Step::Confirm)
+    } else {
+        None
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn navigate(
+    keys: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    mut focus: ResMut<MenuFocus>,
+    mut button_query: Query<
+        (Entity, &mut Interaction, &GlobalTransform, &ViewVisibility),
+        With<Button>,
+    >,
+) {
+    if let Some(entity) = focus.pressed.take() {
+        if let Ok((_, mut interaction, _, _)) = button_query.get_mut(entity) {
+            interaction.set_if_neq(Interaction::None);
+        }
+    }
+
+    // The focused button is gone once its screen is despawned
+    if focus
+        .focused
+        .is_some_and(|entity| !button_query.contains(entity))
+    {
+        focus.focused = None;
+    }
+
+    let Some(step) = read_step(&keys, &gamepad_buttons, &gamepads) else {
+        return;
+    };
+
+    // Top to bottom, then left to right
+    let mut buttons = button_query
+        .iter()
+        .filter(|(_, _, _, visibility)| visibility.get())
+        .map(|(entity, _, transform, _)| (entity, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+    buttons.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    if buttons.is_empty() {
+        return;
+    }
+
+    let current = focus
+        .focused
+        .and_then(|focused| buttons.iter().position(|(entity, _)| *entity == focused));
+
+    match (step, current) {
+        (Step::Confirm, Some(index)) => {
+            let entity = buttons[index].0;
+            if let Ok((_, mut interaction, _, _)) = button_query.get_mut(entity) {
+                *interaction = Interaction::Pressed;
+                focus.pressed = Some(entity);
+            }
+        }
+        (Step::Previous, Some(index)) => {
+            focus.focused = Some(buttons[(index + buttons.len() - 1) % buttons.len()].0)
+        }
+        (Step::Next, Some(index)) => focus.focused = Some(buttons[(index + 1) % buttons.len()].0),
+        // Any input focuses the first button so nothing is pressed by accident
+        (_, None) => focus.focused = Some(buttons[0].0),
+    }
+}
+
+fn highlight(
+    focus: Res<MenuFocus>,
+    mut previous: Local<Option<Entity>>,
+    mut button_query: Query<(&Interaction, &mut BackgroundColor), With<Button>>,
+) {
+    if *previous != focus.focused {
+        if let Some(Ok((Interaction::None, mut color))) = previous.map(|e| button_query.get_mut(e))
+        {
+            *color = colors::NORMAL_BUTTON.into();
+        }
+        *previous = focus.focused;
+    }
+
+    if let Some(Ok((Interaction::None, mut color))) = focus.focused.map(|e| button_query.get_mut(e))
+    {
+        if color.0 != colors::HOVERED_BUTTON {
+            *color = colors::HOVERED_BUTTON.into();
+        }
+    }
+}
@@ -4,6 +4,7 @@ use crate::despawn;
 use crate::state::{AppState, ForState, GameState};
 
 use super::assets::UiAssets;
+use super::start::StartMenuState;
 
 // This plugin manages the pause menu
 pub struct PausedMenuPlugin;
@@ -12,6 +13,17 @@ impl Plugin for PausedMenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Paused), menu_setup)
             .add_systems(OnExit(GameState::Paused), despawn::<PausedMenuScreen>)
+            // The settings screens replace this menu while they are open
+            .add_systems(
+                OnEnter(StartMenuState::Settings),
+                despawn::<PausedMenuScreen>,
+            )
+            .add_systems(
+                OnEnter(StartMenuState::Disabled),
+                menu_setup
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameState::Paused)),
+            )
             .add_systems(Update, menu_action.run_if(in_state(AppState::InGame)));
     }
 }
@@ -23,6 +35,7 @@ struct PausedMenuScreen;
 #[derive(Component)]
 enum MenuButtonAction {
     Resume,
+    Settings,
     Quit,
 }
 
@@ -81,6 +94,29 @@ fn menu_setup(mut commands: Commands, assets: Res<UiAssets>) {
                         ..default()
                     },));
                 });
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style { ..default() },
+                        background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                        ..default()
+                    },
+                    MenuButtonAction::Settings,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((TextBundle {
+                        style: Style { ..default() },
+                        text: Text::from_section(
+                            "settings",
+                            TextStyle {
+                                font: assets.font.clone(),
+                                font_size: 50.0,
+                                color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                            },
+                        ),
+                        ..default()
+                    },));
+                });
             parent
                 .spawn((
                     ButtonBundle {
@@ -115,6 +151,7 @@ fn menu_action(
     >,
     mut app_state: ResMut<NextState<AppState>>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut menu_state: ResMut<NextState<StartMenuState>>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
@@ -126,6 +163,7 @@ fn menu_action(
                 MenuButtonAction::Resume => {
                     game_state.set(GameState::Running);
                 }
+                MenuButtonAction::Settings => menu_state.set(StartMenuState::Settings),
             }
         }
     }
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
+use crate::asset::LoadingAssets;
 use crate::despawn;
-use crate::game::GameData;
+use crate::game::campaign::{self, Campaign};
+use crate::game::score::{self, Score};
+use crate::game::{GameData, GameSeed};
 use crate::state::{AppState, ForState, GameState};
 
 use super::assets::UiAssets;
@@ -11,9 +14,12 @@ pub struct PostGamePlugin;
 
 impl Plugin for PostGamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(AppState::PostGame), setup)
-            .add_systems(OnExit(AppState::PostGame), despawn::<PostGameScreen>)
-            .add_systems(Update, menu_action.run_if(in_state(AppState::PostGame)));
+        app.add_systems(
+            OnEnter(AppState::PostGame),
+            setup.after(campaign::complete_level).after(score::finish),
+        )
+        .add_systems(OnExit(AppState::PostGame), despawn::<PostGameScreen>)
+        .add_systems(Update, menu_action.run_if(in_state(AppState::PostGame)));
     }
 }
 
@@ -23,10 +29,34 @@ struct PostGameScreen;
 // All actions that can be triggered from a button click
 #[derive(Component)]
 enum MenuButtonAction {
-    Continue,
+    NextLevel,
+    Retry,
+    Menu,
 }
 
-fn setup(mut commands: Commands, assets: Res<UiAssets>, game_data: Res<GameData>) {
+fn setup(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    game_data: Res<GameData>,
+    campaign: Res<Campaign>,
+    score: Res<Score>,
+    seed: Res<GameSeed>,
+) {
+    let mut buttons = Vec::new();
+    if game_data.won && campaign.has_next() {
+        buttons.push(("next level", MenuButtonAction::NextLevel));
+    }
+    buttons.push(("retry", MenuButtonAction::Retry));
+    buttons.push(("menu", MenuButtonAction::Menu));
+
+    let breakdown = [
+        format!("presents {}", score.base),
+        format!("combos {} (best x{})", score.combo_bonus, score.best_combo),
+        format!("time bonus {}", score.time_bonus),
+        format!("score {}", score.total()),
+        stars_label(score.stars),
+    ];
+
     let title = if game_data.won {
         "You Won!"
     } else {
@@ -75,45 +105,90 @@ fn setup(mut commands: Commands, assets: Res<UiAssets>, game_data: Res<GameData>
                 ),
                 ..default()
             },));
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: Style { ..default() },
-                        background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
-                        ..default()
+            for line in breakdown {
+                parent.spawn((TextBundle {
+                    style: Style { ..default() },
+                    text: Text::from_section(
+                        line,
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                        },
+                    ),
+                    ..default()
+                },));
+            }
+            parent.spawn((TextBundle {
+                style: Style { ..default() },
+                text: Text::from_section(
+                    format!("seed {}", seed.current),
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 20.0,
+                        color: Color::rgb_u8(0xe0, 0x1b, 0x24),
                     },
-                    MenuButtonAction::Continue,
-                ))
-                .with_children(|parent| {
-                    parent.spawn((TextBundle {
-                        style: Style { ..default() },
-                        text: Text::from_section(
-                            "continue",
-                            TextStyle {
-                                font: assets.font.clone(),
-                                font_size: 50.0,
-                                color: Color::rgb_u8(0xe0, 0x1b, 0x24),
-                            },
-                        ),
-                        ..default()
-                    },));
-                });
+                ),
+                ..default()
+            },));
+            for (label, action) in buttons {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((TextBundle {
+                            style: Style { ..default() },
+                            text: Text::from_section(
+                                label,
+                                TextStyle {
+                                    font: assets.font.clone(),
+                                    font_size: 50.0,
+                                    color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                                },
+                            ),
+                            ..default()
+                        },));
+                    });
+            }
         });
 }
 
+pub fn stars_label(stars: usize) -> String {
+    format!("{}{}", "*".repeat(stars), "-".repeat(3 - stars.min(3)))
+}
+
 #[allow(clippy::type_complexity)]
 fn menu_action(
+    mut commands: Commands,
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
         (Changed<Interaction>, With<Button>),
     >,
     mut app_state: ResMut<NextState<AppState>>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut campaign: ResMut<Campaign>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match menu_button_action {
-                MenuButtonAction::Continue => {
+                MenuButtonAction::NextLevel => {
+                    campaign.next();
+                    commands.insert_resource(LoadingAssets::default());
+                    app_state.set(AppState::Loading);
+                    game_state.set(GameState::Running);
+                }
+                MenuButtonAction::Retry => {
+                    commands.insert_resource(LoadingAssets::default());
+                    app_state.set(AppState::Loading);
+                    game_state.set(GameState::Running);
+                }
+                MenuButtonAction::Menu => {
                     app_state.set(AppState::StartMenu);
                     game_state.set(GameState::Running);
                 }
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+
+use crate::despawn;
+use crate::settings::{
+    next_option, FrameLimit, Settings, FRAME_LIMIT_OPTIONS, MSAA_OPTIONS, SENSITIVITY_OPTIONS,
+    UI_SCALE_OPTIONS, ZOOM_SPEED_OPTIONS,
+};
+use crate::state::{AppState, ForState, GameState};
+
+use super::assets::UiAssets;
+use super::start::StartMenuState;
+
+// This plugin manages the settings screen. It is part of the start menu but can also be
+// opened from the pause menu
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(StartMenuState::Settings), setup)
+            .add_systems(OnExit(StartMenuState::Settings), despawn::<SettingsScreen>)
+            .add_systems(OnExit(GameState::Paused), close)
+            .add_systems(
+                Update,
+                (menu_action, update_labels).run_if(in_state(StartMenuState::Settings)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsScreen;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Setting {
+    Msaa,
+    Vsync,
+    FrameLimit,
+    Sensitivity,
+    ZoomSpeed,
+    UiScale,
+}
+
+const SETTINGS: [Setting; 6] = [
+    Setting::Msaa,
+    Setting::Vsync,
+    Setting::FrameLimit,
+    Setting::Sensitivity,
+    Setting::ZoomSpeed,
+    Setting::UiScale,
+];
+
+impl Setting {
+    fn label(&self, settings: &Settings) -> String {
+        match self {
+            Setting::Msaa => match settings.msaa_samples {
+                0 | 1 => "anti aliasing off".into(),
+                samples => format!("anti aliasing {}x", samples),
+            },
+            Setting::Vsync => format!("vsync {}", if settings.vsync { "on" } else { "off" }),
+            Setting::FrameLimit => match settings.frame_limit {
+                FrameLimit::Auto => "frame limit auto".into(),
+                FrameLimit::Fps(fps) => format!("frame limit {}", fps),
+                FrameLimit::Off => "frame limit off".into(),
+            },
+            Setting::Sensitivity => format!("camera sensitivity {}", settings.camera_sensitivity),
+            Setting::ZoomSpeed => format!("zoom speed {}", settings.zoom_speed),
+            Setting::UiScale => format!("ui scale {}", settings.ui_scale),
+        }
+    }
+
+    fn cycle(&self, settings: &mut Settings) {
+        match self {
+            Setting::Msaa => {
+                settings.msaa_samples = next_option(&MSAA_OPTIONS, settings.msaa_samples)
+            }
+            Setting::Vsync => settings.vsync = !settings.vsync,
+            Setting::FrameLimit => {
+                settings.frame_limit = next_option(&FRAME_LIMIT_OPTIONS, settings.frame_limit)
+            }
+            Setting::Sensitivity => {
+                settings.camera_sensitivity =
+                    next_option(&SENSITIVITY_OPTIONS, settings.camera_sensitivity)
+            }
+            Setting::ZoomSpeed => {
+                settings.zoom_speed = next_option(&ZOOM_SPEED_OPTIONS, settings.zoom_speed)
+            }
+            Setting::UiScale => {
+                settings.ui_scale = next_option(&UI_SCALE_OPTIONS, settings.ui_scale)
+            }
+        }
+    }
+}
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+enum MenuButtonAction {
+    Cycle(Setting),
+    Controls,
+    Back,
+}
+
+// Text showing the current value of a setting
+#[derive(Component)]
+struct SettingText(Setting);
+
+fn setup(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    settings: Res<Settings>,
+    app_state: Res<State<AppState>>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: assets.font.clone(),
+        font_size,
+        color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            ForState {
+                states: vec![AppState::StartMenu],
+            },
+            SettingsScreen,
+        ))
+        .with_children(|parent| {
+            // Keep the game visible behind the menu when opened from the pause menu
+            if *app_state == AppState::StartMenu {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        ..default()
+                    },
+                    image: assets.background.clone(),
+                    ..default()
+                });
+            }
+            parent.spawn(TextBundle::from_section("Settings", text_style(80.0)));
+            for setting in SETTINGS {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        MenuButtonAction::Cycle(setting),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(setting.label(&settings), text_style(35.0)),
+                            SettingText(setting),
+                        ));
+                    });
+            }
+            for (label, action) in [
+                ("controls", MenuButtonAction::Controls),
+                ("back", MenuButtonAction::Back),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, text_style(50.0)));
+                    });
+            }
+        });
+}
+
+fn update_labels(settings: Res<Settings>, mut query: Query<(&mut Text, &SettingText)>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, SettingText(setting)) in query.iter_mut() {
+        if let Some(text) = text.sections.first_mut() {
+            text.value = setting.label(&settings);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn menu_action(
+    interaction_query: Query<
+        (&Interaction, &MenuButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut settings: ResMut<Settings>,
+    mut menu_state: ResMut<NextState<StartMenuState>>,
+    app_state: Res<State<AppState>>,
+) {
+    for (interaction, menu_button_action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match menu_button_action {
+                MenuButtonAction::Cycle(setting) => setting.cycle(&mut settings),
+                MenuButtonAction::Controls => menu_state.set(StartMenuState::Controls),
+                MenuButtonAction::Back => match app_state.get() {
+                    AppState::StartMenu => menu_state.set(StartMenuState::Main),
+                    _ => menu_state.set(StartMenuState::Disabled), // Back to the pause menu
+                },
+            }
+        }
+    }
+}
+
+// Unpausing while the settings are open closes them too
+fn close(
+    menu_state: Res<State<StartMenuState>>,
+    mut next_menu_state: ResMut<NextState<StartMenuState>>,
+) {
+    if matches!(
+        menu_state.get(),
+        StartMenuState::Settings | StartMenuState::Controls
+    ) {
+        next_menu_state.set(StartMenuState::Disabled);
+    }
+}
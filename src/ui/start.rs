@@ -1,10 +1,14 @@
 use bevy::{app::AppExit, prelude::*};
 
-use crate::asset::LoadingAssets;
+use crate::asset::{LoadError, LoadingAssets};
 use crate::despawn;
+use crate::game::campaign::{Campaign, LEVELS};
+use crate::game::GameSeed;
+use crate::replay::Playback;
 use crate::state::{AppState, ForState};
 
 use super::assets::UiAssets;
+use super::post::stars_label;
 
 // This plugin manages the start menu
 pub struct StartMenuPlugin;
@@ -15,14 +19,22 @@ impl Plugin for StartMenuPlugin {
             .add_systems(OnEnter(AppState::StartMenu), menu_setup)
             .add_systems(OnEnter(StartMenuState::Main), main_menu_setup)
             .add_systems(OnExit(StartMenuState::Main), despawn::<StartMenuScreen>)
+            .add_systems(OnEnter(StartMenuState::LevelSelect), level_select_setup)
+            .add_systems(
+                OnExit(StartMenuState::LevelSelect),
+                despawn::<LevelSelectScreen>,
+            )
             .add_systems(Update, menu_action.run_if(in_state(AppState::StartMenu)));
     }
 }
 
 // State used for the current menu screen
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-enum StartMenuState {
+pub enum StartMenuState {
     Main,
+    LevelSelect,
+    Settings,
+    Controls,
     #[default]
     Disabled,
 }
@@ -30,19 +42,46 @@ enum StartMenuState {
 #[derive(Component)]
 struct StartMenuScreen;
 
+#[derive(Component)]
+struct LevelSelectScreen;
+
 // All actions that can be triggered from a button click
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    PlayLevel(usize),
+    Settings,
+    BackToMain,
     #[cfg(not(target_arch = "wasm32"))]
     Quit,
 }
 
-fn menu_setup(mut menu_state: ResMut<NextState<StartMenuState>>) {
+fn menu_setup(
+    mut commands: Commands,
+    mut menu_state: ResMut<NextState<StartMenuState>>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut campaign: ResMut<Campaign>,
+    mut seed: ResMut<GameSeed>,
+    playback: Option<Res<Playback>>,
+) {
+    // Replays go straight into their level
+    if let Some(playback) = playback {
+        campaign.current = playback.level();
+        *seed = GameSeed::fixed(playback.seed());
+        commands.insert_resource(LoadingAssets::default());
+        app_state.set(AppState::Loading);
+        menu_state.set(StartMenuState::Disabled);
+        return;
+    }
+
     menu_state.set(StartMenuState::Main);
 }
 
-fn main_menu_setup(mut commands: Commands, assets: Res<UiAssets>) {
+fn main_menu_setup(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    load_error: Option<Res<LoadError>>,
+) {
     commands
         .spawn((
             NodeBundle {
@@ -96,6 +135,29 @@ fn main_menu_setup(mut commands: Commands, assets: Res<UiAssets>) {
                         ..default()
                     },));
                 });
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style { ..default() },
+                        background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                        ..default()
+                    },
+                    MenuButtonAction::Settings,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((TextBundle {
+                        style: Style { ..default() },
+                        text: Text::from_section(
+                            "settings",
+                            TextStyle {
+                                font: assets.font.clone(),
+                                font_size: 50.0,
+                                color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                            },
+                        ),
+                        ..default()
+                    },));
+                });
             #[cfg(not(target_arch = "wasm32"))] // Cannot quit on wasm
             parent
                 .spawn((
@@ -120,6 +182,118 @@ fn main_menu_setup(mut commands: Commands, assets: Res<UiAssets>) {
                         ..default()
                     },));
                 });
+            // Why we are back here rather than playing
+            if let Some(load_error) = load_error {
+                parent.spawn(TextBundle {
+                    style: Style { ..default() },
+                    text: Text::from_section(
+                        load_error.0.clone(),
+                        TextStyle {
+                            font: assets.font.clone(),
+                            font_size: 30.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    ..default()
+                });
+            }
+        });
+}
+
+fn level_select_setup(mut commands: Commands, assets: Res<UiAssets>, campaign: Res<Campaign>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            ForState {
+                states: vec![AppState::StartMenu],
+            },
+            LevelSelectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+                image: assets.background.clone(),
+                ..default()
+            });
+            for (index, level) in LEVELS.iter().enumerate() {
+                let unlocked = campaign.is_unlocked(index);
+                let label = if unlocked {
+                    format!(
+                        "{}. {} {}",
+                        index + 1,
+                        level.name,
+                        stars_label(campaign.records[index].stars)
+                    )
+                } else {
+                    format!("{}. locked", index + 1)
+                };
+                let color = if unlocked {
+                    Color::rgb_u8(0xe0, 0x1b, 0x24)
+                } else {
+                    Color::GRAY
+                };
+
+                let mut button = parent.spawn(ButtonBundle {
+                    style: Style { ..default() },
+                    background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                    ..default()
+                });
+                // Locked levels are shown but cannot be clicked
+                if unlocked {
+                    button.insert(MenuButtonAction::PlayLevel(index));
+                }
+                button.with_children(|parent| {
+                    parent.spawn((TextBundle {
+                        style: Style { ..default() },
+                        text: Text::from_section(
+                            label,
+                            TextStyle {
+                                font: assets.font.clone(),
+                                font_size: 40.0,
+                                color,
+                            },
+                        ),
+                        ..default()
+                    },));
+                });
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style { ..default() },
+                        background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                        ..default()
+                    },
+                    MenuButtonAction::BackToMain,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((TextBundle {
+                        style: Style { ..default() },
+                        text: Text::from_section(
+                            "back",
+                            TextStyle {
+                                font: assets.font.clone(),
+                                font_size: 50.0,
+                                color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+                            },
+                        ),
+                        ..default()
+                    },));
+                });
         });
 }
 
@@ -133,17 +307,22 @@ fn menu_action(
     mut app_exit_events: EventWriter<AppExit>,
     mut menu_state: ResMut<NextState<StartMenuState>>,
     mut app_state: ResMut<NextState<AppState>>,
+    mut campaign: ResMut<Campaign>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
             match menu_button_action {
                 #[cfg(not(target_arch = "wasm32"))]
                 MenuButtonAction::Quit => app_exit_events.send(AppExit),
-                MenuButtonAction::Play => {
+                MenuButtonAction::Play => menu_state.set(StartMenuState::LevelSelect),
+                MenuButtonAction::PlayLevel(index) => {
+                    campaign.select(*index);
                     commands.insert_resource(LoadingAssets::default()); // TODO: Just a little hack for testing
                     app_state.set(AppState::Loading);
                     menu_state.set(StartMenuState::Disabled);
                 }
+                MenuButtonAction::Settings => menu_state.set(StartMenuState::Settings),
+                MenuButtonAction::BackToMain => menu_state.set(StartMenuState::Main),
             }
         }
     }
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+use crate::despawn;
+use crate::game::ground_station::{self, SelectedStation, Upgrade, UpgradeStation};
+use crate::game::{GameData, GroundStation};
+use crate::state::{AppState, ForState};
+
+use super::assets::UiAssets;
+
+// This plugin manages the panel shown when a ground station is clicked
+pub struct StationPanelPlugin;
+
+impl Plugin for StationPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(AppState::InGame), despawn::<StationPanel>)
+            .add_systems(
+                Update,
+                (
+                    update_panel.run_if(in_state(AppState::InGame)),
+                    menu_action
+                        .before(ground_station::apply_upgrades)
+                        .run_if(in_state(AppState::InGame)),
+                ),
+            );
+    }
+}
+
+// Holds what the panel is showing so it is only rebuilt when something changes
+#[derive(Component, PartialEq)]
+struct StationPanel {
+    station: Entity,
+    coins: i32,
+    tiers: [usize; 3],
+}
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+enum StationButtonAction {
+    Upgrade(Upgrade),
+    Close,
+}
+
+const UPGRADES: [(Upgrade, &str); 3] = [
+    (Upgrade::Fleet, "more rockets"),
+    (Upgrade::Speed, "faster rockets"),
+    (Upgrade::Cargo, "bigger cargo"),
+];
+
+fn update_panel(
+    mut commands: Commands,
+    assets: Res<UiAssets>,
+    selected: Res<SelectedStation>,
+    game_data: Res<GameData>,
+    station_query: Query<&GroundStation>,
+    panel_query: Query<(Entity, &StationPanel)>,
+) {
+    let wanted = selected.0.and_then(|entity| {
+        station_query.get(entity).ok().map(|station| {
+            (
+                station,
+                StationPanel {
+                    station: entity,
+                    coins: game_data.coins,
+                    tiers: UPGRADES.map(|(upgrade, _)| station.tier(upgrade)),
+                },
+            )
+        })
+    });
+
+    let current = panel_query.get_single().ok();
+    if current.map(|(_, panel)| panel) == wanted.as_ref().map(|(_, panel)| panel) {
+        return;
+    }
+
+    if let Some((entity, _)) = current {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some((station, panel)) = wanted else {
+        return;
+    };
+
+    let text_style = |font_size: f32| TextStyle {
+        font: assets.font.clone(),
+        font_size,
+        color: Color::rgb_u8(0xe0, 0x1b, 0x24),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+                ..default()
+            },
+            ForState {
+                states: vec![AppState::InGame],
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(station.name, text_style(30.0)));
+            parent.spawn(TextBundle::from_section(
+                format!("coins {}", panel.coins),
+                text_style(20.0),
+            ));
+
+            for (upgrade, label) in UPGRADES {
+                let cost = match station.upgrade_cost(upgrade) {
+                    Some(cost) => format!("{} coins", cost),
+                    None => "max".into(),
+                };
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style { ..default() },
+                            background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                            ..default()
+                        },
+                        StationButtonAction::Upgrade(upgrade),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            format!("{} ({})", label, cost),
+                            text_style(25.0),
+                        ));
+                    });
+            }
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style { ..default() },
+                        background_color: BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                        ..default()
+                    },
+                    StationButtonAction::Close,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("close", text_style(25.0)));
+                });
+        })
+        .insert(panel);
+}
+
+#[allow(clippy::type_complexity)]
+fn menu_action(
+    interaction_query: Query<
+        (&Interaction, &StationButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut selected: ResMut<SelectedStation>,
+    mut upgrade_events: EventWriter<UpgradeStation>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                StationButtonAction::Upgrade(upgrade) => {
+                    if let Some(station) = selected.0 {
+                        upgrade_events.send(UpgradeStation {
+                            station,
+                            upgrade: *upgrade,
+                        });
+                    }
+                }
+                StationButtonAction::Close => selected.0 = None,
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use sfu::{
+    asset::{LoadError, LoadingAssets},
+    game::{
+        auto_player::AutoPlayer,
+        campaign::{Campaign, LEVELS},
+        orbit::Orbit,
+        present::Present,
+        rocket::{Rocket, RocketState},
+        score::Score,
+        GameTimer, LevelConfig,
+    },
+    replay::Replay,
+    simulation::{Simulation, STEP},
+    state::{AppState, GameState},
+};
+
+const SEED: u64 = 1;
+
+#[test]
+fn level_loads_and_spawns_presents() {
+    let mut sim = Simulation::new(SEED);
+    sim.start_level(0);
+
+    assert_eq!(sim.state(), AppState::InGame);
+    assert_eq!(sim.presents().len(), 170);
+    assert_eq!(sim.game_data().presents_collected, 0);
+}
+
+#[test]
+fn every_level_spawns_its_presents() {
+    for (index, level) in LEVELS.iter().enumerate() {
+        let mut sim = Simulation::new(SEED);
+        sim.start_level(index);
+
+        let total = sim.app.world.resource::<LevelConfig>().total_presents();
+        assert_eq!(sim.presents().len() as i32, total, "{}", level.path);
+    }
+}
+
+#[test]
+fn broken_levels_go_back_to_the_menu() {
+    let mut sim = Simulation::new(SEED);
+    let missing = sim
+        .app
+        .world
+        .resource::<AssetServer>()
+        .load::<LevelConfig>("levels/missing.level.ron");
+    let mut loading = LoadingAssets::default();
+    loading.0.push(missing.id().into());
+    sim.app.insert_resource(loading);
+    sim.app
+        .world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Loading);
+
+    // Files are read on another thread so give it some real time
+    for _ in 0..1000 {
+        if sim.state() == AppState::StartMenu {
+            break;
+        }
+        sim.app.update();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    assert_eq!(sim.state(), AppState::StartMenu);
+    let error = sim.app.world.resource::<LoadError>();
+    assert!(error.0.contains("levels/missing.level.ron"), "{}", error.0);
+    assert!(!sim.app.world.contains_resource::<LevelConfig>());
+}
+
+// Leaves the few presents closest to a pad and marks them so the rockets can get them all in time
+fn mark_closest(sim: &mut Simulation, count: usize) {
+    let world = &mut sim.app.world;
+    let pads = world
+        .query::<&Rocket>()
+        .iter(world)
+        .map(|rocket| rocket.home.translation)
+        .collect::<Vec<_>>();
+    let mut presents = world
+        .query_filtered::<(Entity, &Orbit), With<Present>>()
+        .iter(world)
+        .map(|(entity, orbit)| {
+            let pos = orbit.position();
+            let distance = pads
+                .iter()
+                .map(|pad| pad.distance(pos))
+                .fold(f32::INFINITY, f32::min);
+            (entity, distance)
+        })
+        .collect::<Vec<_>>();
+    presents.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    let (keep, remove) = presents.split_at(count);
+    for (present, _) in remove {
+        sim.app.world.despawn(*present);
+    }
+    for (present, _) in keep {
+        sim.mark(*present, None);
+    }
+}
+
+#[test]
+fn clearing_the_sky_wins() {
+    let mut sim = Simulation::new(SEED);
+    sim.start_level(0);
+    mark_closest(&mut sim, 3);
+
+    assert!(sim.run_until(120.0, |sim| sim.state() == AppState::PostGame));
+    assert!(sim.game_data().won);
+    assert!(sim.game_data().coins > 0);
+}
+
+#[test]
+fn frame_rate_does_not_change_the_game() {
+    let results = [30.0, 60.0, 144.0].map(|fps| {
+        let mut sim = Simulation::new(SEED);
+        sim.start_level(0);
+        sim.set_frame_rate(fps);
+        mark_closest(&mut sim, 3);
+
+        assert!(sim.run_until(120.0, |sim| sim.state() == AppState::PostGame));
+        let game_data = sim.game_data();
+        (
+            game_data.won,
+            game_data.presents_collected,
+            game_data.presents_broken,
+            game_data.coins,
+            sim.app.world.resource::<GameTimer>().elapsed(),
+            sim.app.world.resource::<Score>().total(),
+        )
+    });
+
+    assert!(results[0].0);
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[0], results[2]);
+}
+
+#[test]
+fn pausing_stops_everything() {
+    let mut sim = Simulation::new(SEED);
+    sim.start_level(0);
+    mark_closest(&mut sim, 3);
+    assert!(sim.run_until(30.0, |sim| {
+        let world = &mut sim.app.world;
+        world
+            .query::<&Rocket>()
+            .iter(world)
+            .any(|rocket| rocket.state == RocketState::Intercepting)
+    }));
+
+    let snapshot = |sim: &mut Simulation| {
+        let world = &mut sim.app.world;
+        let rockets = world
+            .query::<(&Rocket, &Transform)>()
+            .iter(world)
+            .map(|(rocket, transform)| (rocket.state, rocket.fuel, transform.translation))
+            .collect::<Vec<_>>();
+        let game_data = sim.game_data();
+        (
+            game_data.presents_collected,
+            game_data.presents_broken,
+            game_data.coins,
+            sim.app.world.resource::<Score>().total(),
+            sim.app.world.resource::<GameTimer>().elapsed(),
+            rockets,
+        )
+    };
+
+    sim.app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Paused);
+    sim.step(STEP);
+    let paused = snapshot(&mut sim);
+    sim.step(20.0);
+    assert_eq!(snapshot(&mut sim), paused);
+
+    // And carries on once unpaused
+    sim.app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Running);
+    sim.step(1.0);
+    assert_ne!(snapshot(&mut sim), paused);
+}
+
+#[test]
+fn running_out_of_time_loses() {
+    let mut sim = Simulation::new(SEED);
+    sim.start_level(0);
+
+    sim.step(150.0);
+    assert_eq!(sim.state(), AppState::InGame);
+
+    assert!(sim.run_until(20.0, |sim| sim.state() == AppState::PostGame));
+    assert!(!sim.game_data().won);
+    assert_eq!(sim.game_data().presents_collected, 0);
+}
+
+#[test]
+fn auto_player_collects_presents_without_recording_progress() {
+    let mut sim = Simulation::new(SEED);
+    sim.app.insert_resource(AutoPlayer::default());
+    sim.start_level(0);
+
+    assert!(sim.run_until(60.0, |sim| sim.game_data().presents_collected >= 5));
+
+    // End the level early rather than waiting out the clock
+    let mut timer = sim.app.world.resource_mut::<GameTimer>();
+    let elapsed = timer.elapsed();
+    timer.set_duration(elapsed);
+    assert!(sim.run_until(1.0, |sim| sim.state() == AppState::PostGame));
+
+    let campaign = sim.app.world.resource::<Campaign>();
+    assert_eq!(campaign.records[0].score, 0);
+    assert_eq!(campaign.unlocked, 1);
+}
+
+#[test]
+fn replays_play_back_the_same_game() {
+    let mut sim = Simulation::new(SEED);
+    sim.app.insert_resource(AutoPlayer::default());
+    sim.start_level(0);
+    assert!(sim.run_until(170.0, |sim| sim.state() == AppState::PostGame));
+    let recorded = sim.game_data();
+    let (collected, broken, coins) = (
+        recorded.presents_collected,
+        recorded.presents_broken,
+        recorded.coins,
+    );
+    assert!(collected > 0);
+
+    // Played back from the file without the auto player
+    let text = sim.last_replay().unwrap().to_ron().unwrap();
+    let mut replay = Simulation::replay(Replay::parse(&text).unwrap()).unwrap();
+    assert!(replay.run_until(170.0, |sim| sim.state() == AppState::PostGame));
+
+    let played = replay.game_data();
+    assert_eq!(played.presents_collected, collected);
+    assert_eq!(played.presents_broken, broken);
+    assert_eq!(played.coins, coins);
+
+    // Someone else's game does not unlock levels or set records
+    let campaign = replay.app.world.resource::<Campaign>();
+    assert_eq!(campaign.unlocked, 1);
+    assert!(campaign
+        .records
+        .iter()
+        .all(|record| record.score == 0 && record.stars == 0));
+}